@@ -0,0 +1,183 @@
+//! Catalog Module - Installable image flavours and their descriptions
+//! The catalog is fetched from `Config.catalog_url` (a URL or a local file) and cached in the data dir
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::ConfigManager;
+use crate::error::{QeError, ResultExt};
+use crate::docker;
+use crate::paths;
+
+/// Hours before a cached remote catalog is fetched again
+const CATALOG_TTL_HOURS: i64 = 24;
+
+/// Catalog used when no source is configured and nothing is cached
+const BUILTIN_CATALOG: &str = r#"{
+    "flavours": [
+        {
+            "name": "light",
+            "image": "quantum/security:light",
+            "description": "Essential tools for quick assessments",
+            "size": 1288490188,
+            "categories": ["recon", "web", "network"],
+            "architectures": ["amd64", "arm64"]
+        },
+        {
+            "name": "latest",
+            "image": "quantum/security:latest",
+            "description": "Standard toolset for most engagements",
+            "size": 2469606195,
+            "categories": ["recon", "web", "network", "ad", "passwords"],
+            "architectures": ["amd64", "arm64"]
+        },
+        {
+            "name": "full",
+            "image": "quantum/security:full",
+            "description": "Every tool, including wireless, RFID and reverse engineering",
+            "size": 4402341478,
+            "categories": ["recon", "web", "network", "ad", "passwords", "wifi", "rfid", "reverse", "forensics"],
+            "architectures": ["amd64"]
+        }
+    ]
+}"#;
+
+/// List of installable flavours
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Catalog {
+    pub flavours: Vec<Flavour>,
+}
+
+/// An image flavour offered for installation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Flavour {
+    pub name: String,
+    /// Image reference, e.g. `quantum/security:full`
+    pub image: String,
+    pub description: String,
+    /// Approximate size of the image once pulled
+    pub size: u64,
+    /// Tool categories included, e.g. `web`, `ad`, `wifi`
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Architectures the image is published for, e.g. `amd64`
+    #[serde(default)]
+    pub architectures: Vec<String>,
+    #[serde(default)]
+    pub changelog: Vec<ChangelogEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    pub version: String,
+    pub date: String,
+    #[serde(default)]
+    pub changes: Vec<String>,
+}
+
+/// Catalog as cached in the data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedCatalog {
+    source: String,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    catalog: Catalog,
+}
+
+pub struct CatalogManager;
+
+impl CatalogManager {
+    /// Load the catalog, using the cache unless it is stale or `refresh` is set
+    pub fn load(refresh: bool) -> Result<Catalog, QeError> {
+        let source = match ConfigManager::load().catalog_url {
+            Some(source) => ConfigManager::expand("catalog_url", &source)?,
+            None => return Self::builtin(),
+        };
+
+        let cached = Self::read_cache().filter(|c| c.source == source);
+        if let Some(cached) = &cached {
+            let fresh = chrono::Utc::now() - cached.fetched_at < chrono::Duration::hours(CATALOG_TTL_HOURS);
+            if fresh && !refresh {
+                return Ok(cached.catalog.clone());
+            }
+        }
+
+        match Self::fetch(&source) {
+            Ok(catalog) => {
+                Self::write_cache(&source, &catalog)?;
+                Ok(catalog)
+            }
+            // A stale catalog is better than none when the source is unreachable
+            Err(e) => match cached {
+                Some(cached) => {
+                    log::warn!("Using cached catalog, refresh failed: {}", e);
+                    Ok(cached.catalog)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Find a flavour by name
+    pub fn find(name: &str) -> Result<Flavour, QeError> {
+        Self::load(false)?
+            .flavours
+            .into_iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| QeError::NotFound(format!("Unknown flavour: {}", name)))
+    }
+
+    fn builtin() -> Result<Catalog, QeError> {
+        serde_json::from_str(BUILTIN_CATALOG).map_err(|e| format!("Invalid built-in catalog: {}", e).into())
+    }
+
+    /// Read a catalog from a URL or a local file
+    fn fetch(source: &str) -> Result<Catalog, QeError> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            let content = docker::block_on(async {
+                let response = reqwest::get(source)
+                    .await
+                    .context("Failed to fetch catalog")?;
+                if !response.status().is_success() {
+                    return Err(QeError::Network(format!("Failed to fetch catalog: {}", response.status())));
+                }
+                response.text().await.context("Failed to read catalog")
+            })?;
+            return serde_json::from_str(&content)
+                .map_err(|e| QeError::Network(format!("Invalid catalog served by {}: {}", source, e)));
+        }
+
+        // A local file is part of the configuration, not something the network got wrong
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read catalog {}: {}", path, e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| QeError::ConfigInvalid(format!("Invalid catalog file {}: {}", path, e)))
+    }
+
+    fn cache_path() -> PathBuf {
+        paths::cache_dir().join("catalog.json")
+    }
+
+    fn read_cache() -> Option<CachedCatalog> {
+        fs::read_to_string(Self::cache_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    fn write_cache(source: &str, catalog: &Catalog) -> Result<(), QeError> {
+        let path = Self::cache_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let cached = CachedCatalog {
+            source: source.to_string(),
+            fetched_at: chrono::Utc::now(),
+            catalog: catalog.clone(),
+        };
+        let content = serde_json::to_string_pretty(&cached)
+            .map_err(|e| format!("Failed to serialize catalog: {}", e))?;
+        fs::write(&path, content).context("Failed to write catalog cache")
+    }
+}
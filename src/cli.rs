@@ -1,67 +1,277 @@
 //! CLI Module - Command Line Interface handlers
 
 use colored::*;
-#[allow(unused_imports)]
-use crate::container::ContainerManager;
-#[allow(unused_imports)]
-use crate::image::ImageManager;
-use crate::config::ConfigManager;
+use crate::container::{Container, ContainerManager, ContainerStatus};
+use crate::container::{DiffFilter, FileChangeKind, DIFF_NOISE_PATHS};
+use crate::image::{split_reference, ImageManager, Platform};
+use crate::catalog::{CatalogManager, Flavour};
+use crate::completion::{self, CompletionShell};
+use crate::config::{ConfigLayer, ConfigManager, Profile};
+use crate::config_format::{self, ConfigFormat};
+use crate::docker::format_size;
+use crate::manager::{ExegolManager, PruneOptions};
+use crate::output;
+use crate::paths;
+use crate::profile::{ContainerProfile, ProfileManager};
+use crate::credentials::{self, CredentialStore};
+use crate::docker;
+use crate::error::{QeError, ResultExt};
+use crate::registry::{RegistryClient, RemoteManifest, DEFAULT_REGISTRY};
+use crate::signature;
+use crate::utils::{format_age, parse_duration};
+use dialoguer::{Confirm, FuzzySelect, Input, Password, Select};
+use std::io::IsTerminal;
 
-pub type CliResult = Result<(), String>;
+pub type CliResult = Result<(), QeError>;
 
 /// Install a new security environment image
-pub fn install(name: Option<String>, tag: Option<String>) -> CliResult {
-    println!("{}", "🔧 Installation d'une image...".cyan());
+pub fn install(
+    name: Option<String>,
+    flavour: Option<String>,
+    tag: Option<String>,
+    platform: Option<String>,
+    insecure: bool,
+) -> CliResult {
+    say!("{}", t!("install.title").cyan());
     
-    let image_name = name.unwrap_or_else(|| "quantum-security".to_string());
-    let image_tag = tag.unwrap_or_else(|| "latest".to_string());
+    let flavour = match flavour {
+        Some(flavour) => Some(CatalogManager::find(&flavour)?),
+        None if name.is_none() && tag.is_none() && std::io::stdin().is_terminal() => Some(pick_flavour()?),
+        None => None,
+    };
     
-    println!("  • Image: {}:{}", image_name, image_tag);
+    let (image_name, image_tag) = match flavour {
+        Some(flavour) => {
+            say!("  {}", t!("install.flavour", name = flavour.name, description = flavour.description));
+            split_reference(&flavour.image)
+        }
+        None => {
+            let (default_name, default_tag) = split_reference(&ConfigManager::load().default_image);
+            (name.unwrap_or(default_name), tag.unwrap_or(default_tag))
+        }
+    };
+    let reference = format!("{}:{}", image_name, image_tag);
+    let platform = platform.as_deref().map(Platform::parse).transpose()?;
     
-    // TODO: Implement actual Docker image pull
-    println!("{}", "  ✓ Image".green());
+    say!("  {}", t!("common.image", name = reference));
+    
+    match ImageManager::remote_platforms(&reference) {
+        Ok(offered) if !offered.is_empty() => {
+            let names: Vec<String> = offered.iter().map(|p| p.to_string()).collect();
+            say!("  {}", t!("install.platforms", platforms = names.join(", ")));
+            
+            let wanted = platform.clone().unwrap_or_else(Platform::host);
+            if !offered.iter().any(|p| wanted.matches(p)) {
+                return Err(QeError::NotFound(format!(
+                    "Image {} is not available for {} (use --platform with one of: {})",
+                    reference,
+                    wanted,
+                    names.join(", ")
+                )));
+            }
+        }
+        Ok(_) => {}
+        Err(e) => say!("  {} {}", t!("install.platforms_unknown").yellow(), e),
+    }
+    warn_if_emulated(platform.as_ref());
+    
+    let verified = if insecure {
+        say!("  {}", t!("common.insecure").yellow());
+        None
+    } else {
+        let verification = ImageManager::verify_remote(&reference)?;
+        say!("  {}", t!("common.signature_verified", key = verification.key, source = verification.source));
+        Some(verification)
+    };
+    
+    let digest = verified.as_ref().map(|v| v.digest.as_str());
+    let image = ImageManager::pull(&image_name, &image_tag, digest, platform.as_ref())?;
+    
+    // The registry must have served the manifest whose signature was checked
+    if let Some(verification) = verified {
+        if image.digest.as_deref() != Some(verification.digest.as_str()) {
+            return Err(format!(
+                "Pulled manifest {} differs from verified {}",
+                image.digest.unwrap_or_default(),
+                verification.digest
+            )
+            .into());
+        }
+    }
+    
+    say!("  {}", t!("install.done").green());
     Ok(())
 }
 
+/// Let the user choose a flavour from the catalog
+fn pick_flavour() -> Result<Flavour, QeError> {
+    let mut flavours = CatalogManager::load(false)?.flavours;
+    if flavours.is_empty() {
+        return Err(QeError::NotFound("The flavour catalog is empty".to_string()));
+    }
+    
+    let items: Vec<String> = flavours
+        .iter()
+        .map(|f| format!("{:<10} {:>9}  {}", f.name, ImageManager::format_size(f.size), f.description))
+        .collect();
+    let selection = Select::new()
+        .with_prompt(t!("install.pick_flavour"))
+        .items(&items)
+        .default(0)
+        .interact()
+        .context("Flavour selection failed")?;
+    
+    Ok(flavours.swap_remove(selection))
+}
+
+/// Fail when a value is missing and there is no terminal to ask for it
+fn require_terminal(message: String) -> Result<(), QeError> {
+    if std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+    Err(QeError::InvalidUsage(message))
+}
+
+/// Let the user search a list, Escape cancelling
+fn fuzzy_pick(prompt: &str, items: &[String]) -> Result<usize, QeError> {
+    FuzzySelect::new()
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact_opt()
+        .context("Selection failed")?
+        .ok_or(QeError::Cancelled)
+}
+
+/// The container given with `--name`, or one chosen among the managed containers
+///
+/// `none` is the message shown when no container passes `wanted`.
+fn container_or_pick(name: Option<String>, none: String, wanted: impl Fn(&Container) -> bool) -> Result<String, QeError> {
+    if let Some(name) = name {
+        return Ok(name);
+    }
+    require_terminal(t!("pick.no_terminal_container", flag = "--name"))?;
+    
+    let mut containers: Vec<Container> = ContainerManager::list()?.into_iter().filter(|c| wanted(c)).collect();
+    if containers.is_empty() {
+        return Err(QeError::NotFound(none));
+    }
+    
+    let items: Vec<String> = containers
+        .iter()
+        .map(|c| format!("{:<30} {:<30} {}", c.name, c.image, c.status))
+        .collect();
+    let selection = fuzzy_pick(&t!("pick.container"), &items)?;
+    Ok(containers.swap_remove(selection).name)
+}
+
+/// The image given with `flag`, or one chosen among the local images
+fn image_or_pick(name: Option<String>, flag: &str) -> Result<String, QeError> {
+    if let Some(name) = name {
+        return Ok(name);
+    }
+    require_terminal(t!("pick.no_terminal_image", flag = flag))?;
+    
+    let images: Vec<_> = ImageManager::list()?.into_iter().filter(|i| !i.dangling).collect();
+    if images.is_empty() {
+        return Err(QeError::NotFound(t!("pick.no_image")));
+    }
+    
+    let items: Vec<String> = images
+        .iter()
+        .map(|i| format!("{:<45} {:>9}", i.reference(), ImageManager::format_size(i.size)))
+        .collect();
+    let selection = fuzzy_pick(&t!("pick.image"), &items)?;
+    Ok(images[selection].reference())
+}
+
+/// Ask before a destructive action, unless `--yes` was given
+fn confirm(prompt: &str, yes: bool) -> Result<(), QeError> {
+    if yes {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(QeError::InvalidUsage(t!("pick.confirm_no_terminal")));
+    }
+    
+    let confirmed = Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .context("Failed to read confirmation")?;
+    if confirmed {
+        Ok(())
+    } else {
+        Err(QeError::Cancelled)
+    }
+}
+
+fn is_running(container: &Container) -> bool {
+    matches!(container.status, ContainerStatus::Running)
+}
+
 /// Start a container with selected environment
-pub fn start(name: Option<String>, image: Option<String>) -> CliResult {
-    println!("{}", "🚀 Démarrage du conteneur...".cyan());
+pub fn start(name: Option<String>, image: Option<String>, insecure: bool) -> CliResult {
+    let container_name = container_or_pick(name, t!("pick.no_stopped_container"), |c| !is_running(c))?;
+    let image_name = match image {
+        Some(image) => image,
+        // An existing container already has its image
+        None => match ContainerManager::list()?.into_iter().find(|c| c.name == container_name) {
+            Some(container) => container.image,
+            None => image_or_pick(None, "--image")?,
+        },
+    };
+    say!("{}", t!("start.title").cyan());
     
-    let container_name = name.unwrap_or_else(|| "quantum-container".to_string());
-    let image_name = image.unwrap_or_else(|| "quantum-security:latest".to_string());
+    say!("  {}", t!("common.container", name = container_name));
+    say!("  {}", t!("common.image", name = image_name));
     
-    println!("  • Conteneur: {}", container_name);
-    println!("  • Image: {}", image_name);
+    // A failed update check must not prevent working with the installed images
+    if let Err(e) = ExegolManager::auto_update(insecure) {
+        say!("  {} {}", t!("start.auto_update_failed").yellow(), e);
+    }
+    
+    if insecure {
+        say!("  {}", t!("common.insecure").yellow());
+    } else {
+        let verification = signature::verify_local(&image_name)?;
+        say!("  {}", t!("common.signature_verified", key = verification.key, source = verification.source));
+    }
+    
+    let (repository, tag) = split_reference(&image_name);
+    if let Ok(image) = ImageManager::info(&repository, &tag) {
+        warn_if_emulated(image.platform.as_ref());
+    }
     
     // TODO: Implement container start logic
-    println!("{}", "  ✓ Conteneur démarré".green());
+    say!("  {}", t!("start.done").green());
     Ok(())
 }
 
 /// Stop a running container
 pub fn stop(name: Option<String>) -> CliResult {
-    println!("{}", "🛑 Arrêt du conteneur...".cyan());
+    let container_name = container_or_pick(name, t!("pick.no_running_container"), is_running)?;
+    say!("{}", t!("stop.title").cyan());
     
-    let container_name = name.unwrap_or_else(|| "quantum-container".to_string());
-    println!("  • Conteneur: {}", container_name);
+    say!("  {}", t!("common.container", name = container_name));
     
     // TODO: Implement container stop logic
-    println!("{}", "  ✓ Conteneur arrêté".green());
+    say!("  {}", t!("stop.done").green());
     Ok(())
 }
 
 /// Execute command in running container
 pub fn exec(name: Option<String>, command: Vec<String>) -> CliResult {
-    let container_name = name.unwrap_or_else(|| "quantum-container".to_string());
+    let container_name = container_or_pick(name, t!("pick.no_running_container"), is_running)?;
     
     if command.is_empty() {
-        println!("{}", "🔌 Connexion au conteneur...".cyan());
-        println!("  • Conteneur: {}", container_name);
+        say!("{}", t!("exec.connecting").cyan());
+        say!("  {}", t!("common.container", name = container_name));
         // Interactive shell
-        println!("{}", "  ✓ Session interactive".green());
+        say!("  {}", t!("exec.interactive").green());
     } else {
-        println!("{}", "⚡ Exécution de la commande...".cyan());
-        println!("  • Commande: {}", command.join(" "));
+        say!("{}", t!("exec.running").cyan());
+        say!("  {}", t!("exec.command", command = command.join(" ")));
         // TODO: Execute command in container
     }
     Ok(())
@@ -69,142 +279,1019 @@ pub fn exec(name: Option<String>, command: Vec<String>) -> CliResult {
 
 /// List available images
 pub fn list_images() -> CliResult {
-    println!("{}", "📦 Images disponibles:".cyan());
-    println!();
+    say!("{}", t!("images.title").cyan());
+    say!();
     
-    // Mock data for demonstration
-    println!("  {:<40} {:<15} {:<15}", "REPOSITORY", "TAG", "SIZE".bold());
-    println!("  {}", "-".repeat(75));
-    println!("  {:<40} {:<15} {:<15}", "quantum/security", "latest", "2.3 GB");
-    println!("  {:<40} {:<15} {:<15}", "quantum/security", "full", "4.1 GB");
-    println!("  {:<40} {:<15} {:<15}", "quantum/security", "light", "1.2 GB");
+    let mut images = ImageManager::list()?;
+    // Finding the platform costs an inspect per image, only done when it is printed
+    let options = output::options();
+    let platform_shown = match &options.template {
+        Some(template) => template.contains(".platform"),
+        None => !options.quiet && options.mode != output::OutputMode::Ids,
+    };
+    if platform_shown {
+        ImageManager::add_platforms(&mut images);
+    }
+    let default_image = ConfigManager::load().default_image;
     
-    Ok(())
+    output::print_list(&images, |image| image.id.clone(), |images| {
+        say!(
+            "  {:<2}{:<35} {:<12} {:<14} {:<21} {:<14} {:<16} {:<10}",
+            "",
+            t!("table.repository"),
+            t!("table.tag"),
+            t!("table.image_id"),
+            t!("table.digest"),
+            t!("table.platform"),
+            t!("table.created"),
+            t!("table.size").bold()
+        );
+        say!("  {}", "-".repeat(127));
+    
+        for image in images {
+            let marker = if image.reference() == default_image { "*" } else { "" };
+            let digest = image
+                .digest
+                .as_deref()
+                .map(|d| d[..d.len().min(19)].to_string())
+                .unwrap_or_else(|| "<none>".to_string());
+            let age = chrono::DateTime::parse_from_rfc3339(&image.created)
+                .map(|c| format_age(c.timestamp()))
+                .unwrap_or_default();
+        
+            let platform = image
+                .platform
+                .as_ref()
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+        
+            let line = format!(
+                "{:<2}{:<35} {:<12} {:<14} {:<21} {:<14} {:<16} {:<10}",
+                marker,
+                image.repository,
+                image.tag,
+                image.short_id(),
+                digest,
+                platform,
+                age,
+                ImageManager::format_size(image.size)
+            );
+            if image.dangling {
+                say!("  {} {}", line.dimmed(), t!("images.dangling").yellow());
+            } else {
+                say!("  {}", line);
+            }
+        }
+    
+        if images.is_empty() {
+            say!("  {}", t!("images.none"));
+        } else {
+            say!();
+            say!("  {}", t!("images.default", image = default_image));
+        }
+    })
+}
+
+/// List flavours available for installation
+pub fn list_available(refresh: bool) -> CliResult {
+    say!("{}", t!("available.title").cyan());
+    say!();
+    
+    let catalog = CatalogManager::load(refresh)?;
+    let installed: Vec<String> = ImageManager::list()
+        .map(|images| images.iter().map(|i| i.reference()).collect())
+        .unwrap_or_default();
+    
+    output::print_list(&catalog.flavours, |flavour| flavour.name.clone(), |flavours| {
+        say!(
+            "  {:<2}{:<10} {:<28} {:<10} {:<14} {}",
+            "",
+            t!("table.flavour"),
+            t!("table.image"),
+            t!("table.size"),
+            t!("table.arch"),
+            t!("table.categories").bold()
+        );
+        say!("  {}", "-".repeat(100));
+        for flavour in flavours {
+            let marker = if installed.contains(&flavour.image) { "✓" } else { "" };
+            say!(
+                "  {:<2}{:<10} {:<28} {:<10} {:<14} {}",
+                marker,
+                flavour.name,
+                flavour.image,
+                ImageManager::format_size(flavour.size),
+                flavour.architectures.join(","),
+                flavour.categories.join(", ")
+            );
+            say!("    {}", flavour.description.dimmed());
+            if let Some(entry) = flavour.changelog.first() {
+                say!("    {} ({}): {}", entry.version, entry.date, entry.changes.join("; ").dimmed());
+            }
+        }
+    
+        say!();
+        say!("  {}", t!("available.installed"));
+    })
+}
+
+/// Show manifest, layers and history of a local image
+pub fn inspect_image(reference: String) -> CliResult {
+    let inspection = ImageManager::inspect(&reference)?;
+    
+    output::print_item(&inspection, |inspection| inspection.image.id.clone(), |inspection| {
+        let image = &inspection.image;
+        say!("{}", t!("inspect.title").cyan());
+        say!();
+        say!("  {:<20} {}", t!("label.reference").bold(), image.reference());
+        say!("  {:<20} {}", t!("label.id").bold(), image.id);
+        say!("  {:<20} {}", t!("label.digest").bold(), image.digest.as_deref().unwrap_or("<none>"));
+        say!("  {:<20} {}", t!("label.created").bold(), image.created);
+        say!("  {:<20} {}/{}", t!("label.platform").bold(), inspection.os, inspection.architecture);
+        say!("  {:<20} {:?}", t!("label.entrypoint").bold(), inspection.entrypoint);
+        say!("  {:<20} {:?}", t!("label.cmd").bold(), inspection.cmd);
+        if !inspection.working_dir.is_empty() {
+            say!("  {:<20} {}", t!("label.working_dir").bold(), inspection.working_dir);
+        }
+        if !inspection.user.is_empty() {
+            say!("  {:<20} {}", t!("label.user").bold(), inspection.user);
+        }
+        say!(
+            "  {:<20} {}",
+            t!("label.size").bold(),
+            t!(
+                "inspect.size",
+                total = ImageManager::format_size(inspection.total_size),
+                shared = ImageManager::format_size(inspection.shared_size),
+                own = ImageManager::format_size(inspection.total_size.saturating_sub(inspection.shared_size))
+            )
+        );
+    
+        if !inspection.env.is_empty() {
+            say!();
+            say!("  {}", t!("label.env").bold());
+            for var in &inspection.env {
+                say!("    {}", var);
+            }
+        }
+    
+        if !inspection.labels.is_empty() {
+            say!();
+            say!("  {}", t!("label.labels").bold());
+            let mut labels: Vec<_> = inspection.labels.iter().collect();
+            labels.sort();
+            for (key, value) in labels {
+                say!("    {}={}", key, value);
+            }
+        }
+    
+        say!();
+        say!("  {} {}", t!("label.layers").bold(), t!("inspect.layer_count", count = inspection.manifest.layers.len()));
+        say!("  {:<21} {:<10} {}", t!("table.digest"), t!("table.size"), t!("table.created_by").bold());
+        say!("  {}", "-".repeat(100));
+        for entry in &inspection.history {
+            let digest = entry
+                .digest
+                .as_deref()
+                .map(|d| d[..d.len().min(19)].to_string())
+                .unwrap_or_else(|| "<empty>".to_string());
+            let instruction: String = entry.instruction.chars().take(68).collect();
+            say!(
+                "  {:<21} {:<10} {}",
+                digest,
+                ImageManager::format_size(entry.size),
+                instruction
+            );
+        }
+    })
+}
+
+/// List the tags of a repository on its registry
+pub fn remote_tags(repository: String) -> CliResult {
+    say!("{}", t!("remote.tags_title").cyan());
+    say!("  {}", t!("remote.repository", repository = repository));
+    say!();
+    
+    let mut tags = ImageManager::remote_tags(&repository)?;
+    tags.sort();
+    output::print_list(&tags, |tag| tag.clone(), |tags| {
+        for tag in tags {
+            say!("  {}", tag);
+        }
+        
+        say!();
+        say!("  {}", t!("remote.tag_count", count = tags.len()));
+    })
+}
+
+/// Show the manifest of a remote image without pulling it
+pub fn remote_inspect(reference: String) -> CliResult {
+    let (reference, response) = ImageManager::remote_manifest(&reference)?;
+    
+    let manifest = match &response.manifest {
+        RemoteManifest::Image(manifest) => serde_json::to_value(manifest),
+        RemoteManifest::List(list) => serde_json::to_value(list),
+    }
+    .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    
+    output::print_item(&manifest, |_| response.digest.clone(), |_| {
+        say!("{}", t!("remote.manifest_title").cyan());
+        say!();
+        say!("  {:<20} {}", t!("label.reference").bold(), reference);
+        say!("  {:<20} {}", t!("label.digest").bold(), response.digest);
+        say!("  {:<20} {}", t!("label.media_type").bold(), response.media_type);
+        say!();
+    
+        match &response.manifest {
+            RemoteManifest::List(list) => {
+                say!("  {:<25} {:<21} {}", t!("table.platform"), t!("table.digest"), t!("table.size").bold());
+                say!("  {}", "-".repeat(60));
+                for entry in &list.manifests {
+                    let platform = entry
+                        .platform
+                        .as_ref()
+                        .map(|p| p.to_string())
+                        .unwrap_or_else(|| "unknown".to_string());
+                    say!(
+                        "  {:<25} {:<21} {}",
+                        platform,
+                        &entry.digest[..entry.digest.len().min(19)],
+                        ImageManager::format_size(entry.size)
+                    );
+                }
+            }
+            RemoteManifest::Image(manifest) => {
+                say!("  {:<20} {}", t!("label.config").bold(), manifest.config.digest);
+                say!("  {:<21} {}", t!("table.digest"), t!("table.size").bold());
+                say!("  {}", "-".repeat(40));
+                for layer in &manifest.layers {
+                    say!(
+                        "  {:<21} {}",
+                        &layer.digest[..layer.digest.len().min(19)],
+                        ImageManager::format_size(layer.size)
+                    );
+                }
+                let total: u64 = manifest.layers.iter().map(|l| l.size).sum();
+                say!();
+                say!("  {}", t!("remote.layers", count = manifest.layers.len(), size = ImageManager::format_size(total)));
+            }
+        }
+    })
 }
 
-/// List running containers
+/// List managed containers
 pub fn list_containers() -> CliResult {
-    println!("{}", "🐳 Conteneurs en cours d'exécution:".cyan());
-    println!();
+    say!("{}", t!("ps.title").cyan());
+    say!();
+    
+    let containers = ContainerManager::list()?;
+    
+    output::print_list(&containers, |container| container.id.clone(), |containers| {
+        say!(
+            "  {:<30} {:<30} {:<12} {:<20}",
+            t!("table.name"),
+            t!("table.image"),
+            t!("table.status"),
+            t!("table.ports").bold()
+        );
+        say!("  {}", "-".repeat(95));
+        for container in containers {
+            let ports: Vec<String> = container.ports.iter().map(|p| p.to_string()).collect();
+            say!(
+                "  {:<30} {:<30} {:<12} {:<20}",
+                container.name,
+                container.image,
+                container.status,
+                ports.join(", ")
+            );
+        }
+        
+        if containers.is_empty() {
+            say!("  {}", t!("ps.none"));
+        }
+    })
+}
+
+/// Show filesystem changes of a container against its image
+pub fn diff(
+    name: String,
+    paths: Vec<String>,
+    exclude: Vec<String>,
+    exclude_noise: bool,
+) -> CliResult {
+    let mut filter = DiffFilter {
+        include: paths,
+        exclude,
+    };
+    if exclude_noise {
+        filter.exclude.extend(DIFF_NOISE_PATHS.iter().map(|p| p.to_string()));
+    }
+    
+    let changes = ContainerManager::diff(&name, &filter)?;
+    
+    output::print_list(&changes, |change| change.path.clone(), |changes| {
+        say!("{}", t!("diff.title").cyan());
+        say!("  {}", t!("common.container", name = name));
+        say!();
+    
+        if changes.is_empty() {
+            say!("  {}", t!("common.no_changes"));
+            return;
+        }
+    
+        let (mut added, mut changed, mut deleted) = (0, 0, 0);
+        for change in changes {
+            let marker = match change.kind {
+                FileChangeKind::Added => {
+                    added += 1;
+                    "A".green()
+                }
+                FileChangeKind::Changed => {
+                    changed += 1;
+                    "C".yellow()
+                }
+                FileChangeKind::Deleted => {
+                    deleted += 1;
+                    "D".red()
+                }
+            };
+            say!("  {} {}", marker, change.path);
+        }
     
-    // Mock data for demonstration
-    println!("  {:<30} {:<20} {:<15} {:<15}", "NAME", "IMAGE", "STATUS", "PORTS".bold());
-    println!("  {}", "-".repeat(80));
-    println!("  {:<30} {:<20} {:<15} {:<15}", "quantum-001", "quantum/security:latest", "Running", "2222->22");
-    println!("  {:<30} {:<20} {:<15} {:<15}", "quantum-002", "quantum/security:full", "Running", "3333->3333");
+        say!();
+        say!("  {}", t!("diff.summary", added = added, changed = changed, deleted = deleted));
+    })
+}
+
+/// Show disk space used by managed resources
+pub fn disk_usage() -> CliResult {
+    say!("{}", t!("df.title").cyan());
+    say!();
+    
+    let rows = ExegolManager::disk_usage()?;
+    
+    output::print_list(&rows, |row| row.kind.to_string(), |rows| {
+        say!(
+            "  {:<15} {:<8} {:<8} {:<12} {:<12}",
+            t!("table.type"),
+            t!("table.total"),
+            t!("table.active"),
+            t!("table.size"),
+            t!("table.reclaimable").bold()
+        );
+        say!("  {}", "-".repeat(60));
+        for row in rows {
+            // `kind` stays in English in structured output, only the table is translated
+            let kind = match row.kind {
+                "Images" => t!("df.images"),
+                "Containers" => t!("df.containers"),
+                "Volumes" => t!("df.volumes"),
+                "Build cache" => t!("df.build_cache"),
+                other => other.to_string(),
+            };
+            say!(
+                "  {:<15} {:<8} {:<8} {:<12} {:<12}",
+                kind,
+                row.total,
+                row.active,
+                format_size(row.size),
+                format_size(row.reclaimable)
+            );
+        }
+    })
+}
+
+/// Remove unused managed containers and images
+pub fn prune(
+    containers: bool,
+    images: bool,
+    dangling: bool,
+    older_than: Option<String>,
+    keep_last: Option<usize>,
+    yes: bool,
+) -> CliResult {
+    let mut options = PruneOptions {
+        containers,
+        images: images || keep_last.is_some(),
+        dangling,
+        older_than: older_than.as_deref().map(parse_duration).transpose()?,
+        keep_last,
+    };
+    // Like `docker system prune`, default to stopped containers and dangling images
+    if !options.containers && !options.images && !options.dangling {
+        options.containers = true;
+        options.dangling = true;
+    }
+    
+    say!("{}", t!("prune.title").cyan());
+    
+    let plan = ExegolManager::plan_prune(&options)?;
+    if plan.is_empty() {
+        say!("  {}", t!("prune.nothing"));
+        return Ok(());
+    }
+    
+    for container in &plan.containers {
+        say!("  {}", t!("prune.container", name = container.refs.join(", "), size = format_size(container.size)));
+    }
+    for image in &plan.images {
+        say!("  {}", t!("prune.image", name = image.refs.join(", "), size = format_size(image.size)));
+    }
+    say!("  {}", t!("prune.reclaimable", size = format_size(plan.reclaimable())));
+    say!();
+    
+    confirm(&t!("prune.confirm"), yes)?;
+    
+    let reclaimed = ExegolManager::prune(&plan)?;
+    say!("  {}", t!("prune.done", size = format_size(reclaimed)).green());
     
     Ok(())
 }
 
 /// Remove a container
-pub fn remove_container(name: String) -> CliResult {
-    println!("{}", "🗑️  Suppression du conteneur...".cyan());
-    println!("  • Conteneur: {}", name);
+pub fn remove_container(name: Option<String>, yes: bool) -> CliResult {
+    let name = container_or_pick(name, t!("pick.no_container"), |_| true)?;
+    confirm(&t!("remove.confirm", name = name), yes)?;
+    say!("{}", t!("remove.title").cyan());
+    say!("  {}", t!("common.container", name = name));
     
     // TODO: Implement container removal
-    println!("{}", "  ✓ Conteneur supprimé".green());
+    say!("  {}", t!("remove.done").green());
     Ok(())
 }
 
 /// Update images and wrapper
-pub fn update(image: Option<String>) -> CliResult {
-    println!("{}", "🔄 Mise à jour...".cyan());
-    
-    if let Some(img) = image {
-        println!("  • Image: {}", img);
+pub fn update(image: Option<String>, check: bool, insecure: bool) -> CliResult {
+    if check {
+        say!("{}", t!("update.checking").cyan());
     } else {
-        println!("  • Mise à jour complète");
+        say!("{}", t!("update.title").cyan());
     }
     
-    // TODO: Implement update logic
-    println!("{}", "  ✓ Mise à jour terminée".green());
-    Ok(())
+    match &image {
+        Some(img) => say!("  {}", t!("common.image", name = img)),
+        None => say!("  {}", t!("update.all")),
+    }
+    say!();
+    
+    let statuses = ExegolManager::update_images(image.as_deref(), check, insecure)?;
+    
+    output::print_list(&statuses, |status| status.reference.clone(), |statuses| {
+        say!();
+        say!("  {:<45} {:<15} {:<12}", t!("table.image"), t!("table.status"), t!("table.download").bold());
+        say!("  {}", "-".repeat(72));
+        for status in statuses {
+            let state = match (&status.error, status.outdated) {
+                (Some(_), _) => t!("update.unknown").yellow(),
+                (None, true) if check => t!("update.outdated").red(),
+                (None, true) => t!("update.updated").green(),
+                (None, false) => t!("update.up_to_date").green(),
+            };
+            let download = if status.outdated {
+                ImageManager::format_size(status.download_size)
+            } else {
+                "-".to_string()
+            };
+            say!("  {:<45} {:<15} {:<12}", status.reference, state, download);
+            if let Some(error) = &status.error {
+                say!("    {}", error.dimmed());
+            }
+        }
+    
+        let outdated: Vec<_> = statuses.iter().filter(|s| s.outdated).collect();
+        let total: u64 = outdated.iter().map(|s| s.download_size).sum();
+        say!();
+        if check && !outdated.is_empty() {
+            say!(
+                "  {}",
+                t!("update.pending", count = outdated.len(), size = ImageManager::format_size(total))
+            );
+        } else {
+            say!("  {}", t!("update.done").green());
+        }
+    })
 }
 
 /// Build custom image
-pub fn build(dockerfile: Option<String>) -> CliResult {
-    println!("{}", "🔨 Construction de l'image...".cyan());
+pub fn build(dockerfile: Option<String>, tag: Option<String>, platform: Option<String>) -> CliResult {
+    say!("{}", t!("build.title").cyan());
     
     let dockerfile_path = dockerfile.unwrap_or_else(|| "Dockerfile".to_string());
-    println!("  • Dockerfile: {}", dockerfile_path);
+    let image_tag = tag.unwrap_or_else(|| "quantum/custom:latest".to_string());
+    let platform = platform.as_deref().map(Platform::parse).transpose()?;
+    
+    say!("  {}", t!("build.dockerfile", path = dockerfile_path));
+    say!("  {}", t!("common.image", name = image_tag));
+    if let Some(platform) = &platform {
+        say!("  {}", t!("common.platform", platform = platform));
+    }
+    warn_if_emulated(platform.as_ref());
     
-    // TODO: Implement Docker build
-    println!("{}", "  ✓ Image construite".green());
+    let image = ImageManager::build(&dockerfile_path, &image_tag, platform.as_ref())?;
+    
+    say!(
+        "  {}",
+        t!("build.done", id = image.short_id(), size = ImageManager::format_size(image.size)).green()
+    );
     Ok(())
 }
 
+/// Warn when images for a platform will run under emulation on this host
+fn warn_if_emulated(platform: Option<&Platform>) {
+    if let Some(platform) = platform.filter(|p| !p.is_native()) {
+        say!("  {}", t!("common.emulated", platform = platform, host = Platform::host()).yellow());
+    }
+}
+
 /// Display version information
 pub fn version() -> CliResult {
-    println!();
-    println!("{}", "╔═══════════════════════════════════════════════════════════════╗".cyan());
-    println!("{}", "║            QUANTUM EXEGOL - Version Info                     ║".cyan());
-    println!("{}", "╚═══════════════════════════════════════════════════════════════╝".cyan());
-    println!();
-    println!("  {:<20} {}", "Version:".bold(), env!("CARGO_PKG_VERSION"));
-    println!("  {:<20} {}", "Build:".bold(), "Release");
-    println!("  {:<20} {}", "Rust:".bold(), env!("CARGO_PKG_RUST_VERSION"));
-    println!();
-    println!("  {}", "Environmental Cybersecurity Framework".italic());
-    println!();
-    Ok(())
+    let info = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "build": "release",
+        "rust": env!("CARGO_PKG_RUST_VERSION"),
+    });
+    
+    output::print_item(&info, |_| env!("CARGO_PKG_VERSION").to_string(), |_| {
+        say!();
+        say!("{}", "╔═══════════════════════════════════════════════════════════════╗".cyan());
+        say!("{}", "║            QUANTUM EXEGOL - Version Info                     ║".cyan());
+        say!("{}", "╚═══════════════════════════════════════════════════════════════╝".cyan());
+        say!();
+        say!("  {:<20} {}", t!("label.version").bold(), env!("CARGO_PKG_VERSION"));
+        say!("  {:<20} Release", t!("label.build").bold());
+        say!("  {:<20} {}", t!("label.rust").bold(), env!("CARGO_PKG_RUST_VERSION"));
+        say!();
+        say!("  {}", "Environmental Cybersecurity Framework".italic());
+        say!();
+    })
 }
 
 /// Configure Quantum Exegol
-pub fn config() -> CliResult {
-    println!("{}", "⚙️  Configuration de Quantum Exegol".cyan());
-    println!();
+pub fn config_show(origin: bool) -> CliResult {
+    let layered = ConfigManager::load_layered();
+    let values = serde_json::to_value(&layered.config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    
+    output::print_item(&values, |_| ConfigManager::user_config_path().display().to_string(), |values| {
+        say!("{}", t!("config.title").cyan());
+        say!();
+    
+        for (path, source) in &layered.origins {
+            let value = path
+                .split('.')
+                .try_fold(values, |value, key| value.get(key))
+                .map(|value| match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .unwrap_or_default();
+        
+            if origin {
+                let label = if source.layer == ConfigLayer::Default {
+                    source.layer.to_string()
+                } else {
+                    format!("{} ({})", source.layer, source.source)
+                };
+                say!("  {:<25} {:<40} {}", format!("{}:", path).bold(), value, label.dimmed());
+            } else {
+                say!("  {:<25} {}", format!("{}:", path).bold(), value);
+            }
+        }
+    
+        say!();
+        say!("  {}", t!("config.hint").yellow());
+        say!("  {}", ConfigManager::user_config_path().display());
+    })
+}
+
+/// Print the effective value of a config key
+pub fn config_get(key: String) -> CliResult {
+    match ConfigManager::get(&key)? {
+        serde_json::Value::String(value) => println!("{}", value),
+        value => println!(
+            "{}",
+            serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize value: {}", e))?
+        ),
+    }
+    Ok(())
+}
+
+/// Set a config value in the user file
+pub fn config_set(key: String, value: String) -> CliResult {
+    ConfigManager::update(&key, &value)?;
+    say!("  • {} = {}", key.bold(), value);
+    
+    // A higher layer still wins over the user file
+    if let Some(origin) = ConfigManager::load_layered().origins.get(&key) {
+        if origin.layer > ConfigLayer::User {
+            say!("  {}", t!("config.shadowed", layer = origin.layer, source = origin.source).yellow());
+        }
+    }
+    Ok(())
+}
+
+/// Remove a config value from the user file
+pub fn config_unset(key: String) -> CliResult {
+    if ConfigManager::unset(&key)? {
+        say!("  {}", t!("config.unset", key = key.bold()));
+    } else {
+        say!("  {}", t!("config.not_set", key = key, path = ConfigManager::user_config_path().display()));
+    }
+    Ok(())
+}
+
+/// Edit the user config file in $EDITOR, saving it only once it is valid
+pub fn config_edit() -> CliResult {
+    let path = ConfigManager::user_config_path();
+    let original = if path.exists() {
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read config: {}", e))?
+    } else {
+        "{}\n".to_string()
+    };
+    
+    // Edit a copy so an invalid file never replaces the working one
+    let format = ConfigFormat::from_path(&path);
+    let draft = std::env::temp_dir().join(format!(
+        "quantum-exegol-config-{}.{}",
+        std::process::id(),
+        format.extension()
+    ));
+    std::fs::write(&draft, &original).map_err(|e| format!("Failed to write {:?}: {}", draft, e))?;
+    
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    
+    let result = loop {
+        let mut words = editor.split_whitespace();
+        let program = words.next().ok_or("Empty $EDITOR")?;
+        let status = std::process::Command::new(program)
+            .args(words)
+            .arg(&draft)
+            .status()
+            .map_err(|e| format!("Failed to run editor '{}': {}", editor, e))?;
+        if !status.success() {
+            break Err(format!("Editor exited with {}", status).into());
+        }
+        
+        let content = std::fs::read_to_string(&draft).map_err(|e| format!("Failed to read {:?}: {}", draft, e))?;
+        if content == original {
+            say!("  {}", t!("common.no_changes"));
+            break Ok(());
+        }
+        
+        match ConfigManager::check_layer(&content, format) {
+            Ok(layer) => break ConfigManager::write_user_layer(&layer),
+            Err(e) => {
+                say!("  {}", t!("config.invalid", error = e).red());
+                let retry = std::io::stdin().is_terminal()
+                    && Confirm::new()
+                        .with_prompt(t!("config.edit_again"))
+                        .default(true)
+                        .interact()
+                        .unwrap_or(false);
+                if !retry {
+                    break Err(QeError::ConfigInvalid(format!("Configuration not saved, draft kept in {:?}", draft)));
+                }
+            }
+        }
+    };
     
-    // Display current configuration
-    let config = ConfigManager::load();
+    if result.is_ok() {
+        std::fs::remove_file(&draft).ok();
+        say!("  • {}", path.display());
+    }
+    result
+}
+
+/// Restore the default configuration
+pub fn config_reset(yes: bool) -> CliResult {
+    let path = ConfigManager::user_config_path();
+    confirm(&t!("config.reset_confirm", path = path.display()), yes)?;
+    
+    ConfigManager::reset()?;
+    Ok(())
+}
+
+/// Check every config layer
+pub fn config_validate() -> CliResult {
+    let layers = ConfigManager::validate();
+    if layers.is_empty() {
+        say!("  {}", t!("config.no_files"));
+        return Ok(());
+    }
+    
+    let mut invalid = 0;
+    for status in &layers {
+        match &status.error {
+            None => say!("  {} {:<8} {}", "✓".green(), status.origin.layer, status.origin.source),
+            Some(e) => {
+                invalid += 1;
+                say!("  {} {:<8} {}", "✗".red(), status.origin.layer, status.origin.source);
+                for line in e.lines() {
+                    say!("      {}", line.red());
+                }
+            }
+        }
+        for warning in &status.warnings {
+            say!("      {}", format!("⚠ {}", warning).yellow());
+        }
+    }
+    
+    if invalid > 0 {
+        return Err(QeError::ConfigInvalid(format!("{} invalid configuration source(s)", invalid)));
+    }
+    Ok(())
+}
+
+/// Print the user config file path, and the other directories with `--all`
+pub fn config_path(all: bool) -> CliResult {
+    if !all {
+        println!("{}", ConfigManager::user_config_path().display());
+        return Ok(());
+    }
     
-    println!("  {:<25} {}", "Docker socket:".bold(), config.docker_socket);
-    println!("  {:<25} {}", "Default image:".bold(), config.default_image);
-    println!("  {:<25} {}", "Data directory:".bold(), config.data_dir);
-    println!("  {:<25} {}", "Auto-update:".bold(), config.auto_update);
+    println!("{:<10} {}", "file", ConfigManager::user_config_path().display());
+    for (name, path) in paths::all() {
+        println!("{:<10} {}", name, path.display());
+    }
+    Ok(())
+}
+
+/// Export the JSON Schema of the config
+pub fn config_schema(stdout: bool) -> CliResult {
+    if stdout {
+        let schema = serde_json::to_string_pretty(&ConfigManager::schema())
+            .map_err(|e| format!("Failed to serialize schema: {}", e))?;
+        println!("{}", schema);
+        return Ok(());
+    }
     
-    println!();
-    println!("  {}", "Pour modifier la configuration, éditez le fichier:".yellow());
-    println!("  {}", "~/.quantum-exegol/config.json", );
+    let linked = ConfigManager::write_schema()?;
+    say!("  • {}", ConfigManager::schema_path().display());
+    if linked {
+        say!("  {}", t!("config.schema_linked", path = ConfigManager::user_config_path().display()));
+    }
+    Ok(())
+}
+
+/// Rewrite the user config file in another format
+pub fn config_convert(to: String) -> CliResult {
+    let format = ConfigFormat::from_name(&to)?;
+    let (new, old) = ConfigManager::convert(format)?;
     
+    say!("  • {}", new.display());
+    let backup = format!("{}.bak", old.display());
+    if std::path::Path::new(&backup).exists() {
+        say!("  {}", t!("config.backup", path = backup).dimmed());
+    }
+    Ok(())
+}
+
+/// Import the settings of an Exegol config.yml into the user config file
+pub fn config_import(path: Option<String>) -> CliResult {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => dirs::home_dir()
+            .ok_or("Cannot locate the home directory")?
+            .join(config_format::EXEGOL_CONFIG),
+    };
+    say!("{}", t!("config.importing", path = path.display()).cyan());
+    
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let (layer, imported) = config_format::import_exegol(&content)?;
+    if imported.is_empty() {
+        say!("  {}", t!("config.nothing_imported"));
+        return Ok(());
+    }
+    
+    ConfigManager::merge_user_layer(layer)?;
+    for line in imported {
+        say!("  • {}", line);
+    }
     Ok(())
 }
 
 /// Restart a container
 pub fn restart(name: Option<String>) -> CliResult {
-    println!("{}", "🔄 Redémarrage du conteneur...".cyan());
+    let container_name = container_or_pick(name, t!("pick.no_container"), |_| true)?;
+    say!("{}", t!("restart.title").cyan());
     
-    let container_name = name.unwrap_or_else(|| "quantum-container".to_string());
-    println!("  • Conteneur: {}", container_name);
+    say!("  {}", t!("common.container", name = container_name));
     
     // TODO: Implement Docker container restart
-    println!("{}", "  ✓ Conteneur redémarré".green());
+    say!("  {}", t!("restart.done").green());
     Ok(())
 }
 
 /// Uninstall an image
-pub fn uninstall(name: Option<String>) -> CliResult {
-    println!("{}", "🗑️  Désinstallation de l'image...".cyan());
+pub fn uninstall(name: Option<String>, yes: bool) -> CliResult {
+    let image_name = image_or_pick(name, "--name")?;
+    confirm(&t!("uninstall.confirm", name = image_name), yes)?;
+    say!("{}", t!("uninstall.title").cyan());
     
-    let image_name = name.unwrap_or_else(|| "quantum-security".to_string());
-    println!("  • Image: {}", image_name);
+    say!("  {}", t!("common.image", name = image_name));
     
     // TODO: Implement Docker image removal
-    println!("{}", "  ✓ Image désinstallée".green());
+    say!("  {}", t!("uninstall.done").green());
+    Ok(())
+}
+
+/// Store credentials for a registry after checking them
+pub fn login(registry: Option<String>, username: Option<String>, password_stdin: bool) -> CliResult {
+    let registry = credentials::normalize_registry(registry.as_deref().unwrap_or(DEFAULT_REGISTRY));
+    say!("{}", t!("login.title").cyan());
+    say!("  {}", t!("common.registry", name = registry));
+    
+    let username = match username {
+        Some(username) => username,
+        None => Input::new()
+            .with_prompt(t!("login.username"))
+            .interact_text()
+            .context("Username required (use --username)")?,
+    };
+    let password = if password_stdin {
+        let mut password = String::new();
+        std::io::stdin()
+            .read_line(&mut password)
+            .context("Failed to read password from stdin")?;
+        password.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        Password::new()
+            .with_prompt(t!("login.password"))
+            .interact()
+            .context("Password required (use --password-stdin)")?
+    };
+    
+    let mut client = RegistryClient::for_registry(&registry).with_credentials(&username, &password);
+    docker::block_on(client.ping())?;
+    
+    CredentialStore::open()?.set(&registry, &username, &password)?;
+    say!("  {}", t!("login.done").green());
+    Ok(())
+}
+
+/// Forget the credentials of a registry
+pub fn logout(registry: Option<String>) -> CliResult {
+    let registry = credentials::normalize_registry(registry.as_deref().unwrap_or(DEFAULT_REGISTRY));
+    say!("{}", t!("logout.title").cyan());
+    say!("  {}", t!("common.registry", name = registry));
+    
+    if CredentialStore::open()?.remove(&registry)? {
+        say!("  {}", t!("logout.done").green());
+    } else {
+        say!("  {}", t!("logout.none"));
+    }
     Ok(())
 }
 
+/// List registries with stored credentials
+pub fn list_credentials() -> CliResult {
+    say!("{}", t!("credentials.title").cyan());
+    say!();
+    
+    let entries = CredentialStore::open()?.list();
+    
+    output::print_list(&entries, |entry| entry.registry.clone(), |entries| {
+        if entries.is_empty() {
+            say!("  {}", t!("credentials.none"));
+            return;
+        }
+    
+        say!("  {:<35} {:<25} {:<20}", t!("table.registry"), t!("table.username"), t!("table.stored").bold());
+        say!("  {}", "-".repeat(80));
+        for entry in entries {
+            say!(
+                "  {:<35} {:<25} {:<20}",
+                entry.registry,
+                entry.username,
+                format_age(entry.stored_at.timestamp())
+            );
+        }
+    })
+}
+
 /// Activate license
 pub fn activate(key: Option<String>) -> CliResult {
-    println!("{}", "🔐 Activation de la licence...".cyan());
+    say!("{}", t!("activate.title").cyan());
     
     if let Some(license_key) = key {
-        println!("  • Clé: {}", license_key);
+        say!("  {}", t!("activate.key", key = license_key));
         // TODO: Implement license validation
-        println!("{}", "  ✓ Licence activée".green());
+        say!("  {}", t!("activate.done").green());
     } else {
-        println!("  • Mode: Essai gratuit");
-        println!("{}", "  ✓ Mode essai actif".green());
+        say!("  {}", t!("activate.trial"));
+        say!("  {}", t!("activate.trial_done").green());
     }
     
     Ok(())
 }
+
+/// List configured profiles
+pub fn list_profiles() -> CliResult {
+    let profiles = ProfileManager::list();
+    // Scripts get the settings each profile ends up with
+    let resolved: Vec<ContainerProfile> = profiles
+        .keys()
+        .filter_map(|name| ProfileManager::resolve(name).ok())
+        .collect();
+    
+    output::print_list(&resolved, |profile| profile.name.clone(), |_| {
+        if profiles.is_empty() {
+            say!("{}", t!("profile.none"));
+            return;
+        }
+    
+        say!(
+            "{:<20} {:<20} {:<30} {}",
+            t!("table.name"),
+            t!("table.extends"),
+            t!("table.image"),
+            t!("table.description")
+        );
+        for (name, profile) in &profiles {
+            // Show the image the profile ends up with, even when inherited
+            let image = ProfileManager::resolve(name)
+                .map(|p| p.image)
+                .unwrap_or_else(|e| e.to_string().red().to_string());
+            say!(
+                "{:<20} {:<20} {:<30} {}",
+                name,
+                profile.extends.as_deref().unwrap_or("-"),
+                image,
+                profile.description.as_deref().unwrap_or("")
+            );
+        }
+    })
+}
+
+/// Show a profile once inheritance is applied
+pub fn show_profile(name: String) -> CliResult {
+    let profile = ProfileManager::resolve(&name)?;
+    
+    output::print_item(&profile, |profile| profile.name.clone(), |profile| {
+        say!("{}", t!("profile.title", name = profile.name).cyan());
+        if let Some(description) = &profile.description {
+            say!("  {}", description.dimmed());
+        }
+        say!("  {:<15} {}", t!("label.inheritance").bold(), profile.chain.join(" → "));
+        say!("  {:<15} {}", t!("label.image").bold(), profile.image);
+        print_profile_settings(profile);
+    })
+}
+
+/// Add a profile to the user config
+pub fn create_profile(name: String, mut profile: Profile, env: Vec<String>) -> CliResult {
+    for item in env {
+        let (key, value) = item
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid variable '{}', expected KEY=VALUE", item))?;
+        profile.env.insert(key.to_string(), value.to_string());
+    }
+    
+    ProfileManager::create(&name, &profile)?;
+    say!("  {}", t!("profile.created", name = name.bold(), path = ConfigManager::user_config_path().display()));
+    Ok(())
+}
+
+fn print_profile_settings(profile: &ContainerProfile) {
+    say!("  {:<15} {}", t!("label.network").bold(), profile.network_mode);
+    say!("  {:<15} {}", t!("label.shell").bold(), profile.shell);
+    for mount in &profile.mounts {
+        say!("  {:<15} {}", t!("label.mount").bold(), mount);
+    }
+    for port in &profile.ports {
+        say!("  {:<15} {}", t!("label.port").bold(), port);
+    }
+    for (key, value) in &profile.env {
+        say!("  {:<15} {}={}", t!("label.env").bold(), key, value);
+    }
+    if !profile.capabilities.is_empty() {
+        say!("  {:<15} {}", t!("label.capabilities").bold(), profile.capabilities.join(", "));
+    }
+    if let Some(memory) = &profile.memory {
+        say!("  {:<15} {}", t!("label.memory").bold(), memory);
+    }
+    if let Some(cpus) = profile.cpus {
+        say!("  {:<15} {}", t!("label.cpus").bold(), cpus);
+    }
+}
+
+/// Print the completion script of a shell
+pub fn completions(shell: CompletionShell, command: &mut clap::Command) -> CliResult {
+    print!("{}", completion::script(shell, command)?);
+    Ok(())
+}
+
+/// Print the names completing the last word, false when the static completion applies
+pub fn complete(words: &[String]) -> bool {
+    let Some(candidates) = completion::candidates_for(words) else {
+        return false;
+    };
+    let prefix = words.last().map(String::as_str).unwrap_or_default();
+    // Without Docker there is nothing to offer, which is better than file names
+    for name in completion::list(candidates, prefix).unwrap_or_default() {
+        println!("{}", name);
+    }
+    true
+}
@@ -0,0 +1,150 @@
+//! Completion Module - Shell completion scripts generated from the command line definition
+//! Container names and image references are completed at tab time through the hidden `__complete` command
+
+use clap::{Command, ValueEnum};
+use clap_complete::Shell;
+
+use crate::container::ContainerManager;
+use crate::docker;
+use crate::error::QeError;
+use crate::image::{split_reference, ImageManager};
+
+/// Hidden command printing the candidates for the word under the cursor, handled before clap parses the line
+pub const COMPLETE_COMMAND: &str = "__complete";
+
+/// Global options taking a value, skipped when looking for the subcommand
+const GLOBAL_VALUE_OPTIONS: &[&str] = &["--set", "-o", "--output", "--format"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Values completed from Docker rather than from the command line definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Candidates {
+    Containers,
+    Images,
+    /// Repositories without a tag, for `install --name` which takes the tag separately
+    Repositories,
+}
+
+/// Completion script for a shell, the clap one followed by the hook calling `__complete`
+pub fn script(shell: CompletionShell, command: &mut Command) -> Result<String, QeError> {
+    let name = command.get_name().to_string();
+    let (generator, hook) = match shell {
+        CompletionShell::Bash => (Shell::Bash, BASH_HOOK),
+        CompletionShell::Zsh => (Shell::Zsh, ZSH_HOOK),
+        CompletionShell::Fish => (Shell::Fish, FISH_HOOK),
+    };
+
+    let mut buffer = Vec::new();
+    clap_complete::generate(generator, command, &name, &mut buffer);
+    let script = String::from_utf8(buffer).map_err(|e| format!("Invalid completion script: {}", e))?;
+    let hook = hook
+        .replace("{bin}", &name)
+        .replace("{ident}", &name.replace('-', "_"))
+        // Function name clap gives the bash completion
+        .replace("{bash_function}", &format!("_{}", name.replace('-', "__")));
+    Ok(format!("{}\n{}", script, hook))
+}
+
+/// What the last word is expected to be, from the words typed after the program name
+pub fn candidates_for(words: &[String]) -> Option<Candidates> {
+    let (_, typed) = words.split_last()?;
+    let previous = typed.last()?;
+
+    let mut args = typed.iter();
+    let subcommand = loop {
+        let word = args.next()?;
+        if GLOBAL_VALUE_OPTIONS.contains(&word.as_str()) {
+            args.next();
+        } else if !word.starts_with('-') {
+            break word;
+        }
+    };
+    // The option must follow the subcommand, not be the subcommand itself
+    if std::ptr::eq(subcommand, previous) {
+        return None;
+    }
+
+    match (subcommand.as_str(), previous.as_str()) {
+        ("start" | "stop" | "exec" | "remove" | "restart", "-n" | "--name") => Some(Candidates::Containers),
+        ("start", "-i" | "--image") | ("uninstall", "-n" | "--name") => Some(Candidates::Images),
+        ("install", "-n" | "--name") => Some(Candidates::Repositories),
+        _ => None,
+    }
+}
+
+/// Names of managed containers or references of managed images starting with `prefix`
+///
+/// Only lists containers and images, without inspecting them, so completion stays fast.
+pub fn list(candidates: Candidates, prefix: &str) -> Result<Vec<String>, QeError> {
+    let mut names: Vec<String> = match candidates {
+        Candidates::Containers => ContainerManager::list()?.into_iter().map(|c| c.name).collect(),
+        Candidates::Images | Candidates::Repositories => {
+            let references = docker::block_on(docker::list_images())?
+                .into_iter()
+                .filter(ImageManager::is_managed)
+                .flat_map(|image| image.repo_tags)
+                .filter(|reference| reference != "<none>:<none>");
+            if candidates == Candidates::Repositories {
+                references.map(|reference| split_reference(&reference).0).collect()
+            } else {
+                references.collect()
+            }
+        }
+    };
+
+    names.retain(|name| name.starts_with(prefix));
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Bash splits words on `:` (image tags), so the line is passed whole and the part before the last colon removed
+const BASH_HOOK: &str = r#"_{ident}_dynamic() {
+    local line="${COMP_LINE:0:COMP_POINT}"
+    local -a words
+    read -ra words <<< "$line"
+    [[ "$line" == *" " ]] && words+=("")
+
+    local candidates
+    if ! candidates="$("${words[0]}" __complete -- "${words[@]:1}" 2>/dev/null)"; then
+        {bash_function} "$@"
+        return
+    fi
+
+    local cur="${words[-1]}" IFS=$'\n'
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+    if [[ "$cur" == *:* && "$COMP_WORDBREAKS" == *:* ]]; then
+        local colon="${cur%"${cur##*:}"}"
+        COMPREPLY=("${COMPREPLY[@]#"$colon"}")
+    fi
+}
+
+complete -F _{ident}_dynamic -o bashdefault -o default {bin}
+"#;
+
+const ZSH_HOOK: &str = r#"_{ident}_dynamic() {
+    local output
+    if output="$(${words[1]} __complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)"; then
+        local -a candidates
+        candidates=(${(f)output})
+        compadd -a candidates
+    else
+        _{bin} "$@"
+    fi
+}
+
+compdef _{ident}_dynamic {bin}
+"#;
+
+const FISH_HOOK: &str = r#"function __{ident}_dynamic
+    {bin} __complete -- (commandline -opc)[2..-1] (commandline -ct) 2>/dev/null
+end
+
+complete -c {bin} -n '__{ident}_dynamic >/dev/null' -f -a '(__{ident}_dynamic)'
+"#;
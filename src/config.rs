@@ -1,11 +1,26 @@
 //! Configuration Manager Module - Application configuration
+//! Values are layered: defaults, system file, user file, project file, `QE_*` variables, `--set` flags
 
+use log::warn;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::config_format::{self, ConfigFormat};
+use crate::error::{QeError, ResultExt};
+use crate::paths;
+use crate::utils::expand_vars;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct Config {
+    /// Version of the file format, upgraded by the migrations below
+    pub schema_version: u32,
     /// Docker socket path
     pub docker_socket: String,
     /// Default image to use
@@ -22,98 +37,615 @@ pub struct Config {
     pub gpu_enabled: bool,
     /// Network mode
     pub network_mode: String,
+    /// Minimum hours between two automatic update checks
+    #[serde(default = "default_update_interval_hours")]
+    pub update_interval_hours: u64,
+    /// Ed25519 public keys trusted to sign images
+    #[serde(default)]
+    pub trusted_keys: Vec<TrustedKey>,
+    /// Where the registry credential encryption key comes from
+    #[serde(default)]
+    pub credential_key: CredentialKeySource,
+    /// Mirrors tried before the upstream registry, lowest priority first
+    #[serde(default)]
+    pub registry_mirrors: Vec<RegistryMirror>,
+    /// Rules redirecting image names to another registry
+    #[serde(default)]
+    pub registry_rewrites: Vec<RewriteRule>,
+    /// Per-registry TLS settings
+    #[serde(default)]
+    pub registries: Vec<RegistrySettings>,
+    /// URL or local file of the flavour catalog (built-in catalog when unset)
+    #[serde(default)]
+    pub catalog_url: Option<String>,
+    /// Cache directory (defaults to the XDG cache directory or `$QE_HOME/cache`)
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+    /// State directory (defaults to the XDG state directory or `$QE_HOME/state`)
+    #[serde(default)]
+    pub state_dir: Option<String>,
+    /// Log directory (defaults to `logs` in the state directory)
+    #[serde(default)]
+    pub logs_dir: Option<String>,
+    /// Language of the messages: auto (from the locale), en or fr
+    #[serde(default)]
+    pub language: Language,
+    /// Named container settings, managed with the `profile` commands
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Container settings for one kind of engagement
+///
+/// Anything left unset is taken from the profile named in `extends`, then from the top-level config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Profile {
+    /// Profile this one inherits from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Image reference, e.g. `quantum/security:full`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Docker network mode, e.g. `host` or `bridge`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_mode: Option<String>,
+    /// Bind mounts as `host:container[:ro]`, added to the inherited ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<String>,
+    /// Published ports as `[ip:]host:container[/proto]`, added to the inherited ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<String>,
+    /// Environment variables, overriding the inherited ones with the same name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Linux capabilities to add, e.g. `NET_ADMIN`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+    /// Memory limit, e.g. `4g` or `512m`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// Number of CPUs the container may use, e.g. `2` or `1.5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<f64>,
+    /// Shell opened by `exec` and `start`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+}
+
+/// Mirror serving the same repositories as an upstream registry
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RegistryMirror {
+    /// Upstream registry being mirrored, e.g. `docker.io`
+    pub registry: String,
+    /// Mirror host, e.g. `mirror.corp.local:5000`
+    pub endpoint: String,
+    /// Mirrors are tried in increasing priority order
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Rewrite of image names, e.g. `quantum/*` to `registry.corp.local/quantum/*`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RewriteRule {
+    /// Image name pattern, optionally ending with `*`
+    pub from: String,
+    /// Replacement, where `*` stands for the part matched in `from`
+    pub to: String,
+}
+
+/// TLS settings of one registry
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RegistrySettings {
+    /// Registry host, e.g. `registry.corp.local`
+    pub host: String,
+    /// Accept invalid TLS certificates
+    #[serde(default)]
+    pub insecure: bool,
+    /// PEM file of an additional trusted certificate authority
+    #[serde(default)]
+    pub ca_file: Option<String>,
+}
+
+/// Source of the key encrypting stored registry credentials
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialKeySource {
+    /// Random key kept in a file in the data directory
+    #[default]
+    Keyfile,
+    /// Key derived from a passphrase, prompted or read from the environment
+    Passphrase,
+}
+
+/// Language of the messages shown to people
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// Taken from `LC_ALL`, `LC_MESSAGES` or `LANG`
+    #[default]
+    Auto,
+    En,
+    Fr,
+}
+
+/// Public key allowed to sign image manifests
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TrustedKey {
+    /// Name shown when an image is verified with this key
+    pub name: String,
+    /// Raw 32-byte Ed25519 public key, base64-encoded
+    pub public_key: String,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
             docker_socket: get_default_docker_socket(),
             default_image: "quantum/security:latest".to_string(),
-            data_dir: get_default_data_dir(),
+            data_dir: paths::default_data_dir().to_string_lossy().to_string(),
             auto_update: true,
             default_shell: "/bin/bash".to_string(),
-            workspace: get_default_workspace(),
+            workspace: paths::default_workspace().to_string_lossy().to_string(),
             gpu_enabled: false,
             network_mode: "bridge".to_string(),
+            update_interval_hours: default_update_interval_hours(),
+            trusted_keys: Vec::new(),
+            credential_key: CredentialKeySource::default(),
+            registry_mirrors: Vec::new(),
+            registry_rewrites: Vec::new(),
+            registries: Vec::new(),
+            catalog_url: None,
+            cache_dir: None,
+            state_dir: None,
+            logs_dir: None,
+            language: Language::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
 
 pub struct ConfigManager;
 
+/// Version of the config format written by this build
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades of a config file, the one at index N moving it from version N to N + 1
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v0_to_v1];
+
+/// System-wide configuration directory
+const SYSTEM_CONFIG_DIR: &str = "/etc/quantum-exegol";
+
+/// Name of the config files, followed by `.toml`, `.yaml` or `.json`
+const CONFIG_STEM: &str = "config";
+
+/// Per-project config file name, looked up from the current directory upwards
+const PROJECT_CONFIG_STEM: &str = ".quantum-exegol";
+
+/// JSON Schema of the config, written next to the config file for editors
+const SCHEMA_FILE: &str = "config.schema.json";
+
+/// Prefix of the environment variables overriding config keys, e.g. `QE_DEFAULT_IMAGE`
+pub const ENV_PREFIX: &str = "QE_";
+
+/// Keys deciding which images and registries are trusted, only read from the system and user files
+///
+/// A project file comes with whatever repository was cloned, and the environment with whatever
+/// launched the command, so neither may redirect pulls or replace the signing keys.
+const TRUSTED_LAYER_KEYS: &[&str] = &[
+    "trusted_keys",
+    "registry_rewrites",
+    "registry_mirrors",
+    "registries",
+    "docker_socket",
+    "credential_key",
+    "catalog_url",
+];
+
+/// Configuration layers, from lowest to highest precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigLayer {
+    Default,
+    System,
+    User,
+    Project,
+    Environment,
+    CommandLine,
+}
+
+impl ConfigLayer {
+    /// Whether the layer may set the keys in `TRUSTED_LAYER_KEYS`
+    fn is_trusted(self) -> bool {
+        !matches!(self, ConfigLayer::Project | ConfigLayer::Environment)
+    }
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConfigLayer::Default => "default",
+            ConfigLayer::System => "system",
+            ConfigLayer::User => "user",
+            ConfigLayer::Project => "project",
+            ConfigLayer::Environment => "env",
+            ConfigLayer::CommandLine => "cli",
+        };
+        f.pad(name)
+    }
+}
+
+/// Layer and source (file, variable or flag) that set a value
+#[derive(Debug, Clone)]
+pub struct ConfigOrigin {
+    pub layer: ConfigLayer,
+    pub source: String,
+}
+
+/// Effective configuration and where each of its values comes from
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: Config,
+    /// Origin of every value, keyed by dotted path, e.g. `auto_update` or `registries`
+    pub origins: BTreeMap<String, ConfigOrigin>,
+    /// Files, variables and flags applied over the defaults, in order
+    pub layers: Vec<LayerStatus>,
+}
+
+/// Whether a layer could be applied
+#[derive(Debug, Clone)]
+pub struct LayerStatus {
+    pub origin: ConfigOrigin,
+    /// Why the layer was ignored
+    pub error: Option<String>,
+    /// Problems that did not prevent the layer from applying, e.g. unknown keys
+    pub warnings: Vec<String>,
+}
+
+/// `--set key=value` flags, the highest layer
+static CLI_OVERRIDES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Configuration resolved on first use, dropped whenever it is saved
+static LOADED: Mutex<Option<LayeredConfig>> = Mutex::new(None);
+
+/// Validator compiled from the schema on first use
+static VALIDATOR: OnceLock<Option<jsonschema::Validator>> = OnceLock::new();
+
 impl ConfigManager {
+    /// Expand `~` and `${VAR}` in a value where it is used, naming its key when a variable is missing
+    ///
+    /// Values are stored as written, so one unset variable never invalidates the rest of a file.
+    pub fn expand(key: &str, value: &str) -> Result<String, QeError> {
+        expand_vars(value).map_err(|e| QeError::ConfigInvalid(format!("{}: {}", key, e)))
+    }
+
     /// Get config file path
     fn get_config_path() -> PathBuf {
-        let config_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("quantum-exegol");
+        let config_dir = paths::config_dir();
         
         fs::create_dir_all(&config_dir).ok();
-        config_dir.join("config.json")
+        config_format::find_files(&config_dir, CONFIG_STEM)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| config_dir.join("config.json"))
     }
 
-    /// Load configuration from file
+    /// System-wide config file, shared by every user of the machine
+    pub fn system_config_path() -> PathBuf {
+        let config_dir = PathBuf::from(SYSTEM_CONFIG_DIR);
+        config_format::find_files(&config_dir, CONFIG_STEM)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| config_dir.join("config.json"))
+    }
+
+    /// Closest project config file in the current directory or one of its parents
+    pub fn project_config_path() -> Option<PathBuf> {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors()
+            .find_map(|dir| config_format::find_files(dir, PROJECT_CONFIG_STEM).into_iter().next())
+    }
+
+    /// Register the `--set key=value` flags given on the command line
+    pub fn set_cli_overrides(overrides: &[String]) -> Result<(), QeError> {
+        let defaults = defaults_value();
+        let mut parsed = Vec::new();
+        for item in overrides {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| QeError::ConfigInvalid(format!("Invalid --set '{}', expected key=value", item)))?;
+            let key = key.trim().to_string();
+            parse_value(&defaults, &key, value).map_err(QeError::ConfigInvalid)?;
+            parsed.push((key, value.to_string()));
+        }
+
+        CLI_OVERRIDES
+            .set(parsed)
+            .map_err(|_| "Command line overrides already set".into())
+    }
+
+    /// Load the effective configuration
     pub fn load() -> Config {
-        let config_path = Self::get_config_path();
-        
-        if config_path.exists() {
-            match fs::read_to_string(&config_path) {
-                Ok(content) => {
-                    match serde_json::from_str(&content) {
-                        Ok(config) => return config,
-                        Err(e) => {
-                            eprintln!("Warning: Failed to parse config: {}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to read config: {}", e);
+        Self::load_layered().config
+    }
+
+    /// Load the effective configuration with the origin of each value
+    pub fn load_layered() -> LayeredConfig {
+        let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(layered) = loaded.as_ref() {
+            return layered.clone();
+        }
+        let layered = Self::resolve();
+        *loaded = Some(layered.clone());
+        // Released first, the logger loads the config to translate its prefix
+        drop(loaded);
+
+        for status in &layered.layers {
+            for warning in &status.warnings {
+                warn!("{}: {}", status.origin.source, warning);
+            }
+            if let Some(error) = &status.error {
+                warn!("ignoring {}: {}", status.origin.source, error);
+            }
+        }
+        layered
+    }
+
+    /// Merge every layer over the built-in defaults
+    fn resolve() -> LayeredConfig {
+        let mut resolver = Resolver::new();
+
+        let mut files = vec![
+            (ConfigLayer::System, Self::system_config_path()),
+            (ConfigLayer::User, Self::get_config_path()),
+        ];
+        files.extend(Self::project_config_path().map(|path| (ConfigLayer::Project, path)));
+
+        for (layer, path) in files {
+            if !path.exists() {
+                continue;
+            }
+            let origin = ConfigOrigin {
+                layer,
+                source: path.to_string_lossy().to_string(),
+            };
+            // Only the first format found in a directory is read
+            let mut warnings: Vec<String> = match (path.parent(), path.file_stem().and_then(|s| s.to_str())) {
+                (Some(dir), Some(stem)) => config_format::find_files(dir, stem)
+                    .into_iter()
+                    .skip(1)
+                    .map(|other| format!("{} is ignored, remove one of the two files", other.display()))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let layer = Self::read_file_layer(&path, layer == ConfigLayer::User, &mut warnings);
+            resolver.apply_file(origin, layer, warnings);
+        }
+
+        for key in resolver.keys() {
+            let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+            if let Ok(raw) = std::env::var(&name) {
+                let origin = ConfigOrigin {
+                    layer: ConfigLayer::Environment,
+                    source: name,
+                };
+                resolver.apply_setting(origin, &key, &raw);
+            }
+        }
+
+        for (key, raw) in CLI_OVERRIDES.get().into_iter().flatten() {
+            let origin = ConfigOrigin {
+                layer: ConfigLayer::CommandLine,
+                source: format!("--set {}", key),
+            };
+            resolver.apply_setting(origin, key, raw);
+        }
+
+        resolver.finish()
+    }
+
+    /// Read a config file and bring it to the current schema, rewriting the user file in place
+    fn read_file_layer(path: &Path, writable: bool, warnings: &mut Vec<String>) -> Result<Value, String> {
+        let mut layer = config_format::read(path).map_err(|e| e.to_string())?;
+        let from = match migrate(&mut layer)? {
+            Some(from) => from,
+            None => {
+                if schema_version(&layer)? > SCHEMA_VERSION {
+                    warnings.push(format!(
+                        "written for schema v{}, this version only knows v{}",
+                        schema_version(&layer)?,
+                        SCHEMA_VERSION
+                    ));
                 }
+                return Ok(layer);
             }
+        };
+
+        // Shared files are upgraded in memory only, they may belong to someone else
+        if writable {
+            let backup = PathBuf::from(format!("{}.v{}.bak", path.display(), from));
+            fs::copy(path, &backup).map_err(|e| format!("Failed to back up config to {:?}: {}", backup, e))?;
+            config_format::write(path, &layer).map_err(|e| e.to_string())?;
+            warnings.push(format!(
+                "upgraded from schema v{} to v{}, previous file kept as {}",
+                from,
+                SCHEMA_VERSION,
+                backup.display()
+            ));
         }
-        
-        // Return default config
-        Config::default()
+        Ok(layer)
+    }
+
+    /// Check every config file, variable and flag without the cache, returning their status
+    pub fn validate() -> Vec<LayerStatus> {
+        Self::resolve().layers
+    }
+
+    /// Path of the user config file, the one modified by `config set`
+    pub fn user_config_path() -> PathBuf {
+        Self::get_config_path()
+    }
+
+    /// Effective value of a key, e.g. `default_image` or `registries`
+    pub fn get(key: &str) -> Result<Value, QeError> {
+        let values = serde_json::to_value(Self::load())
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        lookup(&values, key)
+            .cloned()
+            .ok_or_else(|| QeError::NotFound(format!("Unknown config key: {}", key)))
+    }
+
+    /// Remove a key from the user config file, returning whether it was set there
+    pub fn unset(key: &str) -> Result<bool, QeError> {
+        if lookup(&defaults_value(), key).is_none() && !key.contains('.') {
+            return Err(QeError::NotFound(format!("Unknown config key: {}", key)));
+        }
+
+        let mut user = Self::read_user_layer()?;
+        let (parent, leaf) = match key.rsplit_once('.') {
+            Some((parent, leaf)) => (lookup_mut(&mut user, parent), leaf),
+            None => (Some(&mut user), key),
+        };
+        let removed = parent
+            .and_then(|parent| parent.as_object_mut())
+            .and_then(|parent| parent.remove(leaf))
+            .is_some();
+
+        if removed {
+            Self::write_user_layer(&user)?;
+        }
+        Ok(removed)
+    }
+
+    /// Check the content of a user config file, as `config edit` does before saving
+    pub fn check_layer(content: &str, format: ConfigFormat) -> Result<Value, QeError> {
+        let layer = format.parse(content).context("Failed to parse config")?;
+        let origin = ConfigOrigin {
+            layer: ConfigLayer::User,
+            source: "edit".to_string(),
+        };
+        apply_layer(&mut defaults_value(), &mut BTreeMap::new(), layer.clone(), &origin)
+            .map_err(QeError::ConfigInvalid)?;
+        Ok(layer)
+    }
+
+    /// Replace the user config file with a checked layer
+    pub fn write_user_layer(layer: &Value) -> Result<(), QeError> {
+        let config_path = Self::get_config_path();
+        let mut layer = layer.clone();
+        if let Some(settings) = layer.as_object_mut() {
+            settings.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+        }
+        config_format::write(&config_path, &layer)?;
+        *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        Ok(())
+    }
+
+    /// Settings written in the user config file alone, empty if there is none
+    fn read_user_layer() -> Result<Value, QeError> {
+        let path = Self::get_config_path();
+        if !path.exists() {
+            return Ok(Value::Object(Map::new()));
+        }
+        Self::read_file_layer(&path, true, &mut Vec::new()).map_err(QeError::ConfigInvalid)
     }
 
     /// Save configuration to file
-    pub fn save(config: &Config) -> Result<(), String> {
+    pub fn save(config: &Config) -> Result<(), QeError> {
         let config_path = Self::get_config_path();
         
-        let content = serde_json::to_string_pretty(config)
+        let value = serde_json::to_value(config)
             .map_err(|e| format!("Failed to serialize config: {}", e))?;
         
-        fs::write(&config_path, content)
-            .map_err(|e| format!("Failed to write config: {}", e))?;
+        config_format::write(&config_path, &value)?;
+        *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = None;
         
-        println!("Configuration saved to: {:?}", config_path);
+        say!("{}", t!("config.saved", path = config_path.display()));
         Ok(())
     }
 
-    /// Initialize default configuration
-    pub fn init() -> Result<Config, String> {
-        let config = Config::default();
-        Self::save(&config)?;
-        Ok(config)
+    /// Empty the user config file, so only the system file and the built-in defaults apply
+    pub fn reset() -> Result<(), QeError> {
+        Self::write_user_layer(&Value::Object(Map::new()))?;
+        say!("{}", t!("config.saved", path = Self::get_config_path().display()));
+        Ok(())
     }
 
-    /// Update a specific config value
-    pub fn update(key: &str, value: &str) -> Result<(), String> {
-        let mut config = Self::load();
-        
-        match key {
-            "docker_socket" => config.docker_socket = value.to_string(),
-            "default_image" => config.default_image = value.to_string(),
-            "data_dir" => config.data_dir = value.to_string(),
-            "auto_update" => config.auto_update = value.parse().unwrap_or(true),
-            "default_shell" => config.default_shell = value.to_string(),
-            "workspace" => config.workspace = value.to_string(),
-            "gpu_enabled" => config.gpu_enabled = value.parse().unwrap_or(false),
-            "network_mode" => config.network_mode = value.to_string(),
-            _ => return Err(format!("Unknown config key: {}", key)),
+    /// JSON Schema of the config file, described by the doc comments of `Config`
+    pub fn schema() -> Value {
+        schemars::schema_for!(Config).to_value()
+    }
+
+    /// Where `config schema` writes the schema
+    pub fn schema_path() -> PathBuf {
+        paths::config_dir().join(SCHEMA_FILE)
+    }
+
+    /// Write the schema next to the config file and point the user file at it
+    ///
+    /// Returns whether the user file had to be linked to the schema.
+    pub fn write_schema() -> Result<bool, QeError> {
+        // Creates the config directory if needed
+        let config_path = Self::get_config_path();
+        let schema_path = Self::schema_path();
+        let content = serde_json::to_string_pretty(&Self::schema())
+            .map_err(|e| format!("Failed to serialize schema: {}", e))?;
+        fs::write(&schema_path, content + "\n").map_err(|e| format!("Failed to write schema: {}", e))?;
+
+        if !config_path.exists() {
+            Self::write_user_layer(&Value::Object(Map::new()))?;
         }
-        
-        Self::save(&config)
+        config_format::link_schema(&config_path, &format!("./{}", SCHEMA_FILE))
+    }
+
+    /// Set a value in the user config file, parsed to the type of the key
+    pub fn update(key: &str, value: &str) -> Result<(), QeError> {
+        let parsed = parse_value(&defaults_value(), key, value).map_err(QeError::ConfigInvalid)?;
+        Self::merge_user_layer(nested(key, parsed))
+    }
+
+    /// Switch the user config file to another format, returning the new and the old path
+    pub fn convert(format: ConfigFormat) -> Result<(PathBuf, PathBuf), QeError> {
+        let old = Self::get_config_path();
+        if ConfigFormat::from_path(&old) == format {
+            return Err(QeError::Conflict(format!("{} is already in {} format", old.display(), format)));
+        }
+
+        let mut layer = Self::read_user_layer()?;
+        let linked = layer.as_object_mut().and_then(|settings| settings.remove("$schema"));
+        let new = old.with_extension(format.extension());
+        config_format::write(&new, &layer)?;
+        if let Some(Value::String(schema)) = linked {
+            config_format::link_schema(&new, &schema)?;
+        }
+
+        // The old file would shadow or be shadowed by the new one, keep it aside
+        if old.exists() {
+            let backup = PathBuf::from(format!("{}.bak", old.display()));
+            fs::rename(&old, &backup).map_err(|e| format!("Failed to move {:?} aside: {}", old, e))?;
+        }
+        *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        Ok((new, old))
+    }
+
+    /// Merge settings into the user config file, refusing those that would make it invalid
+    pub fn merge_user_layer(layer: Value) -> Result<(), QeError> {
+        let mut user = Self::read_user_layer()?;
+
+        let mut merged = defaults_value();
+        let origin = ConfigOrigin {
+            layer: ConfigLayer::User,
+            source: Self::get_config_path().to_string_lossy().to_string(),
+        };
+        let mut origins = BTreeMap::new();
+        apply_layer(&mut merged, &mut origins, user.clone(), &origin).map_err(|e| {
+            QeError::ConfigInvalid(format!("{} is invalid, fix it with `config edit`: {}", origin.source, e))
+        })?;
+        apply_layer(&mut merged, &mut origins, layer.clone(), &origin).map_err(QeError::ConfigInvalid)?;
+
+        merge(&mut user, layer, "", &origin, &mut origins);
+        Self::write_user_layer(&user)
     }
 }
 
@@ -126,20 +658,520 @@ fn get_default_docker_socket() -> String {
     }
 }
 
-/// Get default data directory
-fn get_default_data_dir() -> String {
-    dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("quantum-exegol")
-        .to_string_lossy()
-        .to_string()
+fn default_update_interval_hours() -> u64 {
+    24
+}
+
+/// Built-in defaults as a JSON tree
+fn defaults_value() -> Value {
+    serde_json::to_value(Config::default()).unwrap_or_default()
+}
+
+/// Value at a dotted path
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+fn lookup_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(value, |value, key| value.get_mut(key))
+}
+
+/// Tree holding a single value at a dotted path
+fn nested(path: &str, value: Value) -> Value {
+    path.rsplit('.').fold(value, |value, key| {
+        let mut map = Map::new();
+        map.insert(key.to_string(), value);
+        Value::Object(map)
+    })
+}
+
+/// Parse a raw string into the type the key has in the defaults
+fn parse_value(defaults: &Value, key: &str, raw: &str) -> Result<Value, String> {
+    let top = key.split('.').next().unwrap_or(key);
+    if defaults.get(top).is_none() {
+        return Err(format!("Unknown config key: {}", top));
+    }
+
+    match lookup(defaults, key) {
+        Some(Value::Bool(_)) => match raw.trim().to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Ok(Value::Bool(true)),
+            "false" | "no" | "off" | "0" => Ok(Value::Bool(false)),
+            _ => Err(format!("Invalid value for {}: expected true or false, got '{}'", key, raw)),
+        },
+        Some(Value::Number(_)) => raw
+            .trim()
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| format!("Invalid value for {}: expected a positive integer, got '{}'", key, raw)),
+        Some(Value::String(_)) => Ok(Value::String(raw.to_string())),
+        // Optional strings are unset by an empty value
+        Some(Value::Null) if raw.is_empty() => Ok(Value::Null),
+        Some(Value::Null) => Ok(Value::String(raw.to_string())),
+        Some(Value::Array(_)) | Some(Value::Object(_)) => {
+            serde_json::from_str(raw).map_err(|e| format!("Invalid value for {}: expected JSON, {}", key, e))
+        }
+        // Entries of maps and lists take JSON, or a plain string when it does not parse
+        None => Ok(serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))),
+    }
+}
+
+/// Merge a layer over the current tree, keeping it only if the result is still a valid config
+///
+/// Returns the keys of the layer that no setting uses.
+fn apply_layer(
+    merged: &mut Value,
+    origins: &mut BTreeMap<String, ConfigOrigin>,
+    mut layer: Value,
+    origin: &ConfigOrigin,
+) -> Result<Vec<String>, String> {
+    if !layer.is_object() {
+        return Err("expected an object of settings".to_string());
+    }
+    // Editors read `$schema`, it is not a setting
+    if let Some(settings) = layer.as_object_mut() {
+        settings.remove("$schema");
+    }
+
+    let errors = schema_errors(&layer);
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    // Unknown keys are looked up over the defaults so those of lower layers are not reported again
+    let mut alone = defaults_value();
+    merge(&mut alone, layer.clone(), "", origin, &mut BTreeMap::new());
+    let mut unknown = Vec::new();
+    serde_ignored::deserialize::<_, _, Config>(alone, |path| unknown.push(path.to_string()))
+        .map_err(|e| e.to_string())?;
+
+    let mut candidate = merged.clone();
+    let mut candidate_origins = origins.clone();
+    merge(&mut candidate, layer, "", origin, &mut candidate_origins);
+    serde_json::from_value::<Config>(candidate.clone()).map_err(|e| e.to_string())?;
+    for path in &unknown {
+        candidate_origins.remove(path);
+    }
+
+    *merged = candidate;
+    *origins = candidate_origins;
+    Ok(unknown)
+}
+
+/// Layers merged over the built-in defaults, applied from the lowest precedence to the highest
+struct Resolver {
+    defaults: Value,
+    merged: Value,
+    origins: BTreeMap<String, ConfigOrigin>,
+    layers: Vec<LayerStatus>,
+}
+
+impl Resolver {
+    fn new() -> Self {
+        let defaults = defaults_value();
+        let mut origins = BTreeMap::new();
+        record_origins(
+            &defaults,
+            "",
+            &ConfigOrigin {
+                layer: ConfigLayer::Default,
+                source: "built-in".to_string(),
+            },
+            &mut origins,
+        );
+        Self {
+            merged: defaults.clone(),
+            defaults,
+            origins,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Top-level keys, each of which can be set from the environment
+    fn keys(&self) -> Vec<String> {
+        self.defaults.as_object().map(|o| o.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Apply the settings of a file, or record why it could not be read
+    fn apply_file(&mut self, origin: ConfigOrigin, layer: Result<Value, String>, mut warnings: Vec<String>) {
+        let layer = layer.map(|mut layer| {
+            if let Some(settings) = layer.as_object_mut().filter(|_| !origin.layer.is_trusted()) {
+                for key in TRUSTED_LAYER_KEYS {
+                    if settings.remove(*key).is_some() {
+                        warnings.push(untrusted_key_warning(key, origin.layer));
+                    }
+                }
+            }
+            layer
+        });
+        let result = layer.and_then(|layer| apply_layer(&mut self.merged, &mut self.origins, layer, &origin));
+        let error = match result {
+            Ok(unknown) => {
+                if !unknown.is_empty() {
+                    warnings.push(format!("unknown keys ignored: {}", unknown.join(", ")));
+                }
+                None
+            }
+            Err(e) => Some(e),
+        };
+        self.layers.push(LayerStatus { origin, error, warnings });
+    }
+
+    /// Apply one `key=value` setting from a variable or a flag
+    fn apply_setting(&mut self, origin: ConfigOrigin, key: &str, raw: &str) {
+        let top = key.split('.').next().unwrap_or(key);
+        if !origin.layer.is_trusted() && TRUSTED_LAYER_KEYS.contains(&top) {
+            let warnings = vec![untrusted_key_warning(top, origin.layer)];
+            self.layers.push(LayerStatus {
+                origin,
+                error: None,
+                warnings,
+            });
+            return;
+        }
+        let result = parse_value(&self.defaults, key, raw)
+            .and_then(|value| apply_layer(&mut self.merged, &mut self.origins, nested(key, value), &origin));
+        self.layers.push(LayerStatus {
+            origin,
+            error: result.err(),
+            warnings: Vec::new(),
+        });
+    }
+
+    fn finish(self) -> LayeredConfig {
+        // Every layer was checked when applied, so this only fails on a bug
+        let config = serde_json::from_value(self.merged).unwrap_or_default();
+        LayeredConfig {
+            config,
+            origins: self.origins,
+            layers: self.layers,
+        }
+    }
+}
+
+fn untrusted_key_warning(key: &str, layer: ConfigLayer) -> String {
+    format!("{} ignored, it can only be set in the system or user config, not the {} layer", key, layer)
+}
+
+/// Schema violations of a layer, each prefixed with the dotted path of the value
+fn schema_errors(layer: &Value) -> Vec<String> {
+    let validator = VALIDATOR.get_or_init(|| jsonschema::validator_for(&ConfigManager::schema()).ok());
+    let Some(validator) = validator else {
+        return Vec::new();
+    };
+
+    validator
+        .iter_errors(layer)
+        .map(|error| {
+            let path = error.instance_path().to_string();
+            match path.trim_start_matches('/').replace('/', ".") {
+                path if path.is_empty() => error.to_string(),
+                path => format!("{}: {}", path, error),
+            }
+        })
+        .collect()
+}
+
+/// Version a config layer declares, files from before versioning being version 0
+fn schema_version(layer: &Value) -> Result<u32, String> {
+    match layer.get("schema_version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid schema_version: {}", version)),
+    }
 }
 
-/// Get default workspace directory
-fn get_default_workspace() -> String {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("quantum-workspace")
-        .to_string_lossy()
-        .to_string()
+/// Bring a config layer to the current schema, returning the version it had if it changed
+fn migrate(layer: &mut Value) -> Result<Option<u32>, String> {
+    let version = schema_version(layer)?;
+    let Some(settings) = layer.as_object_mut() else {
+        return Ok(None);
+    };
+    if version >= SCHEMA_VERSION {
+        return Ok(None);
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(settings);
+    }
+    settings.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+    Ok(Some(version))
+}
+
+/// Version 1 only introduced `schema_version`, which `migrate` stamps on every upgraded file
+fn migrate_v0_to_v1(_settings: &mut Map<String, Value>) {}
+
+/// Deep-merge objects, any other value replaces the one below it
+fn merge(base: &mut Value, layer: Value, prefix: &str, origin: &ConfigOrigin, origins: &mut BTreeMap<String, ConfigOrigin>) {
+    let (Value::Object(base), Value::Object(layer)) = (base, layer) else {
+        return;
+    };
+
+    for (key, value) in layer {
+        let path = join_path(prefix, &key);
+        match base.get_mut(&key) {
+            Some(existing) if existing.is_object() && value.is_object() => {
+                merge(existing, value, &path, origin, origins);
+            }
+            _ => {
+                origins.retain(|p, _| !p.starts_with(&format!("{}.", path)));
+                record_origins(&value, &path, origin, origins);
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Attribute every leaf of a value to an origin
+fn record_origins(value: &Value, path: &str, origin: &ConfigOrigin, origins: &mut BTreeMap<String, ConfigOrigin>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                record_origins(value, &join_path(path, key), origin, origins);
+            }
+        }
+        _ if !path.is_empty() => {
+            // A leaf replaces a parent that was itself a leaf, e.g. an empty map
+            let mut parent = path;
+            while let Some((head, _)) = parent.rsplit_once('.') {
+                origins.remove(head);
+                parent = head;
+            }
+            origins.insert(path.to_string(), origin.clone());
+        }
+        _ => {}
+    }
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn origin(layer: ConfigLayer, source: &str) -> ConfigOrigin {
+        ConfigOrigin {
+            layer,
+            source: source.to_string(),
+        }
+    }
+
+    /// Every layer kind, each overriding `workspace` and one key of its own
+    fn layered() -> LayeredConfig {
+        let mut resolver = Resolver::new();
+        resolver.apply_file(
+            origin(ConfigLayer::System, "/etc/config.json"),
+            Ok(json!({"workspace": "/system", "default_shell": "/bin/sh", "network_mode": "host"})),
+            Vec::new(),
+        );
+        resolver.apply_file(
+            origin(ConfigLayer::User, "~/config.json"),
+            Ok(json!({"workspace": "/user", "default_shell": "/bin/zsh"})),
+            Vec::new(),
+        );
+        resolver.apply_file(
+            origin(ConfigLayer::Project, ".quantum-exegol.json"),
+            Ok(json!({"workspace": "/project", "gpu_enabled": true})),
+            Vec::new(),
+        );
+        resolver.apply_setting(origin(ConfigLayer::Environment, "QE_WORKSPACE"), "workspace", "/env");
+        resolver.apply_setting(origin(ConfigLayer::Environment, "QE_NETWORK_MODE"), "network_mode", "none");
+        resolver.apply_setting(origin(ConfigLayer::CommandLine, "--set workspace"), "workspace", "/cli");
+        resolver.finish()
+    }
+
+    #[test]
+    fn higher_layers_win() {
+        let layered = layered();
+        assert_eq!(layered.config.workspace, "/cli");
+        assert_eq!(layered.config.network_mode, "none");
+        assert_eq!(layered.config.default_shell, "/bin/zsh");
+        assert!(layered.config.gpu_enabled);
+        assert_eq!(layered.config.default_image, Config::default().default_image);
+        assert!(layered.layers.iter().all(|l| l.error.is_none() && l.warnings.is_empty()));
+    }
+
+    #[test]
+    fn origins_name_the_layer_that_set_each_value() {
+        let origins = layered().origins;
+        assert_eq!(origins["workspace"].layer, ConfigLayer::CommandLine);
+        assert_eq!(origins["network_mode"].source, "QE_NETWORK_MODE");
+        assert_eq!(origins["gpu_enabled"].layer, ConfigLayer::Project);
+        assert_eq!(origins["default_shell"].source, "~/config.json");
+        assert_eq!(origins["default_image"].layer, ConfigLayer::Default);
+    }
+
+    #[test]
+    fn nested_origins_follow_the_replaced_value() {
+        let mut resolver = Resolver::new();
+        resolver.apply_file(
+            origin(ConfigLayer::User, "~/config.json"),
+            Ok(json!({"profiles": {"web": {"image": "quantum/web:full"}}})),
+            Vec::new(),
+        );
+        resolver.apply_setting(
+            origin(ConfigLayer::CommandLine, "--set profiles.web.shell"),
+            "profiles.web.shell",
+            "/bin/zsh",
+        );
+        let layered = resolver.finish();
+
+        assert_eq!(layered.origins["profiles.web.image"].layer, ConfigLayer::User);
+        assert_eq!(layered.origins["profiles.web.shell"].layer, ConfigLayer::CommandLine);
+        let web = &layered.config.profiles["web"];
+        assert_eq!(web.image.as_deref(), Some("quantum/web:full"));
+        assert_eq!(web.shell.as_deref(), Some("/bin/zsh"));
+    }
+
+    #[test]
+    fn invalid_layer_is_ignored_and_lower_ones_kept() {
+        let mut resolver = Resolver::new();
+        resolver.apply_file(
+            origin(ConfigLayer::System, "/etc/config.json"),
+            Ok(json!({"auto_update": false})),
+            Vec::new(),
+        );
+        resolver.apply_file(
+            origin(ConfigLayer::User, "~/config.json"),
+            Ok(json!({"auto_update": "sometimes", "workspace": "/user"})),
+            Vec::new(),
+        );
+        resolver.apply_setting(origin(ConfigLayer::Environment, "QE_GPU_ENABLED"), "gpu_enabled", "maybe");
+        let layered = resolver.finish();
+
+        assert!(!layered.config.auto_update);
+        assert_eq!(layered.config.workspace, Config::default().workspace);
+        assert_eq!(layered.origins["auto_update"].layer, ConfigLayer::System);
+        let errors: Vec<_> = layered.layers.iter().map(|l| l.error.is_some()).collect();
+        assert_eq!(errors, [false, true, true]);
+    }
+
+    #[test]
+    fn values_with_unset_variables_are_kept_as_written() {
+        std::env::remove_var("QE_TEST_CONFIG_UNSET");
+        let mut resolver = Resolver::new();
+        resolver.apply_file(
+            origin(ConfigLayer::User, "~/config.json"),
+            Ok(json!({"workspace": "${QE_TEST_CONFIG_UNSET}/work", "auto_update": false})),
+            Vec::new(),
+        );
+        let layered = resolver.finish();
+
+        assert!(layered.layers[0].error.is_none());
+        assert_eq!(layered.config.workspace, "${QE_TEST_CONFIG_UNSET}/work");
+        assert!(!layered.config.auto_update);
+        let error = ConfigManager::expand("workspace", &layered.config.workspace).unwrap_err();
+        assert!(error.to_string().contains("workspace"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_and_not_tracked() {
+        let mut resolver = Resolver::new();
+        resolver.apply_file(
+            origin(ConfigLayer::User, "~/config.json"),
+            Ok(json!({"colour": "blue", "profiles": {"web": {"image": "quantum/web", "memroy": "4g"}}})),
+            vec!["config.toml is ignored, remove one of the two files".to_string()],
+        );
+        let layered = resolver.finish();
+
+        let status = &layered.layers[0];
+        assert!(status.error.is_none());
+        assert_eq!(status.warnings.len(), 2);
+        assert!(status.warnings[1].contains("colour"), "{:?}", status.warnings);
+        assert!(status.warnings[1].contains("profiles.web.memroy"), "{:?}", status.warnings);
+        assert!(!layered.origins.contains_key("colour"));
+        assert!(!layered.origins.contains_key("profiles.web.memroy"));
+        assert_eq!(layered.config.profiles["web"].image.as_deref(), Some("quantum/web"));
+    }
+
+    #[test]
+    fn project_and_env_layers_cannot_change_trust_settings() {
+        let mut resolver = Resolver::new();
+        resolver.apply_file(
+            origin(ConfigLayer::User, "~/config.json"),
+            Ok(json!({"trusted_keys": [{"name": "release", "public_key": "dXNlcg=="}]})),
+            Vec::new(),
+        );
+        resolver.apply_file(
+            origin(ConfigLayer::Project, ".quantum-exegol.json"),
+            Ok(json!({
+                "trusted_keys": [{"name": "attacker", "public_key": "cHJvamVjdA=="}],
+                "registry_mirrors": [{"registry": "docker.io", "endpoint": "evil.example"}],
+                "workspace": "/project"
+            })),
+            Vec::new(),
+        );
+        resolver.apply_setting(
+            origin(ConfigLayer::Environment, "QE_CATALOG_URL"),
+            "catalog_url",
+            "https://evil.example/catalog.json",
+        );
+        let layered = resolver.finish();
+
+        assert_eq!(layered.config.trusted_keys.len(), 1);
+        assert_eq!(layered.config.trusted_keys[0].name, "release");
+        assert_eq!(layered.origins["trusted_keys"].layer, ConfigLayer::User);
+        assert!(layered.config.registry_mirrors.is_empty());
+        assert_eq!(layered.config.catalog_url, None);
+        // The rest of the project file still applies
+        assert_eq!(layered.config.workspace, "/project");
+
+        let project = &layered.layers[1];
+        assert!(project.error.is_none());
+        assert_eq!(project.warnings.len(), 2);
+        assert!(project.warnings[0].starts_with("trusted_keys ignored"), "{:?}", project.warnings);
+        assert!(layered.layers[2].warnings[0].starts_with("catalog_url ignored"));
+    }
+
+    /// Config file with some content in a fresh directory of its own
+    fn config_file(content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qe-config-{}-{:?}", std::process::id(), std::thread::current().id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn unversioned_user_file_is_stamped_and_backed_up() {
+        let original = r#"{"workspace": "/work", "catalog_url": "https://example.com/catalog.json"}"#;
+        let path = config_file(original);
+
+        let mut warnings = Vec::new();
+        let layer = ConfigManager::read_file_layer(&path, true, &mut warnings).unwrap();
+        assert_eq!(
+            layer,
+            json!({"workspace": "/work", "catalog_url": "https://example.com/catalog.json", "schema_version": SCHEMA_VERSION})
+        );
+        assert_eq!(config_format::read(&path).unwrap(), layer);
+        let backup = PathBuf::from(format!("{}.v0.bak", path.display()));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+        assert!(warnings[0].contains("upgraded from schema v0"), "{:?}", warnings);
+
+        // Already current, nothing is rewritten
+        let mut warnings = Vec::new();
+        assert_eq!(ConfigManager::read_file_layer(&path, true, &mut warnings).unwrap(), layer);
+        assert!(warnings.is_empty());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn shared_files_are_migrated_in_memory_only() {
+        let original = r#"{"workspace": "/work"}"#;
+        let path = config_file(original);
+
+        let layer = ConfigManager::read_file_layer(&path, false, &mut Vec::new()).unwrap();
+        assert_eq!(layer["schema_version"], SCHEMA_VERSION);
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert!(!PathBuf::from(format!("{}.v0.bak", path.display())).exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
 }
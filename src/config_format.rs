@@ -0,0 +1,259 @@
+//! Config Format Module - JSON, TOML and YAML config files
+//! TOML files are updated in place so their comments and layout survive a save
+
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::error::{QeError, ResultExt};
+
+/// Exegol settings file that `config import` reads by default
+pub const EXEGOL_CONFIG: &str = ".exegol/config.yml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Formats in the order files are looked up when several exist
+    pub const ALL: [ConfigFormat; 3] = [ConfigFormat::Toml, ConfigFormat::Yaml, ConfigFormat::Json];
+
+    pub fn from_name(name: &str) -> Result<Self, QeError> {
+        match name.to_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            other => Err(QeError::InvalidUsage(format!(
+                "Unknown config format: {} (expected json, toml or yaml)",
+                other
+            ))),
+        }
+    }
+
+    /// Format of a file from its extension, JSON when there is none
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| Self::from_name(ext).ok())
+            .unwrap_or(ConfigFormat::Json)
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ConfigFormat::Json => &["json"],
+            ConfigFormat::Toml => &["toml"],
+            ConfigFormat::Yaml => &["yaml", "yml"],
+        }
+    }
+
+    /// Extension of the files written in this format
+    pub fn extension(self) -> &'static str {
+        self.extensions()[0]
+    }
+
+    pub fn parse(self, content: &str) -> Result<Value, QeError> {
+        let parsed = match self {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            // An empty YAML file is a null document
+            ConfigFormat::Yaml => serde_yaml::from_str::<Option<Value>>(content)
+                .map(|value| value.unwrap_or_else(|| Value::Object(Map::new())))
+                .map_err(|e| e.to_string()),
+        };
+        parsed.map_err(QeError::ConfigInvalid)
+    }
+
+    /// Render settings, reusing the previous content of a TOML file to keep its comments
+    pub fn render(self, value: &Value, previous: Option<&str>) -> Result<String, QeError> {
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(value)
+                .map(|content| content + "\n")
+                .map_err(|e| format!("Failed to serialize config: {}", e).into()),
+            // Comments at the top of a YAML file, such as the schema modeline, are kept
+            ConfigFormat::Yaml => serde_yaml::to_string(value)
+                .map(|content| leading_comments(previous) + &content)
+                .map_err(|e| format!("Failed to serialize config: {}", e).into()),
+            ConfigFormat::Toml => render_toml(value, previous),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.extension())
+    }
+}
+
+/// Existing files named `<stem>.<ext>` in a directory, in lookup order
+pub fn find_files(dir: &Path, stem: &str) -> Vec<PathBuf> {
+    ConfigFormat::ALL
+        .iter()
+        .flat_map(|format| format.extensions())
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Read a config file in the format given by its extension
+pub fn read(path: &Path) -> Result<Value, QeError> {
+    let content = fs::read_to_string(path).context("Failed to read config")?;
+    ConfigFormat::from_path(path).parse(&content).context("Failed to parse config")
+}
+
+/// Write settings to a file in the format given by its extension
+pub fn write(path: &Path, value: &Value) -> Result<(), QeError> {
+    let previous = fs::read_to_string(path).ok();
+    let content = ConfigFormat::from_path(path).render(value, previous.as_deref())?;
+    fs::write(path, content).context("Failed to write config")
+}
+
+fn render_toml(value: &Value, previous: Option<&str>) -> Result<String, QeError> {
+    // TOML has no null, an unset optional value is simply absent
+    let value = strip_nulls(value);
+    let fresh: DocumentMut = toml::to_string(&value)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?
+        .parse()
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    let previous = previous.and_then(|content| {
+        let document = content.parse::<DocumentMut>().ok()?;
+        let settings = toml::from_str::<Value>(content).ok()?;
+        Some((document, settings))
+    });
+
+    match (previous, &value) {
+        (Some((mut document, settings)), Value::Object(new)) => {
+            let old = settings.as_object().cloned().unwrap_or_default();
+            sync_table(document.as_table_mut(), fresh.as_table(), &old, new);
+            Ok(document.to_string())
+        }
+        _ => Ok(fresh.to_string()),
+    }
+}
+
+/// Bring `table` to the content of `fresh`, leaving the items whose value did not change untouched
+fn sync_table(table: &mut Table, fresh: &Table, old: &Map<String, Value>, new: &Map<String, Value>) {
+    let stale: Vec<String> = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !fresh.contains_key(key))
+        .collect();
+    for key in stale {
+        table.remove(&key);
+    }
+
+    for (key, item) in fresh.iter() {
+        if old.get(key) == new.get(key) && table.contains_key(key) {
+            continue;
+        }
+
+        match (table.get_mut(key), item) {
+            (Some(Item::Table(existing)), Item::Table(item)) => {
+                let empty = Map::new();
+                let old = old.get(key).and_then(Value::as_object).unwrap_or(&empty);
+                let new = new.get(key).and_then(Value::as_object).unwrap_or(&empty);
+                sync_table(existing, item, old, new);
+            }
+            // Keep the comment that trails the old value
+            (Some(Item::Value(existing)), Item::Value(value)) => {
+                let decor = existing.decor().clone();
+                *existing = value.clone();
+                *existing.decor_mut() = decor;
+            }
+            (Some(existing), item) => *existing = item.clone(),
+            (None, item) => {
+                table.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+fn leading_comments(previous: Option<&str>) -> String {
+    previous
+        .unwrap_or_default()
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Point a config file at a JSON Schema the way editors expect for its format
+///
+/// Returns false if the file was already linked.
+pub fn link_schema(path: &Path, schema: &str) -> Result<bool, QeError> {
+    let content = fs::read_to_string(path).context("Failed to read config")?;
+
+    let (directive, linked) = match ConfigFormat::from_path(path) {
+        ConfigFormat::Json => {
+            let mut value = ConfigFormat::Json.parse(&content).context("Failed to parse config")?;
+            let settings = value
+                .as_object_mut()
+                .ok_or_else(|| QeError::ConfigInvalid("Config file is not an object".to_string()))?;
+            if settings.get("$schema").and_then(Value::as_str) == Some(schema) {
+                return Ok(false);
+            }
+            settings.insert("$schema".to_string(), Value::String(schema.to_string()));
+            return write(path, &value).map(|_| true);
+        }
+        // Taplo (Even Better TOML) directive
+        ConfigFormat::Toml => (format!("#:schema {}", schema), "#:schema"),
+        ConfigFormat::Yaml => (format!("# yaml-language-server: $schema={}", schema), "# yaml-language-server:"),
+    };
+
+    if content.lines().any(|line| line.starts_with(linked)) {
+        return Ok(false);
+    }
+    fs::write(path, format!("{}\n{}", directive, content)).context("Failed to write config")?;
+    Ok(true)
+}
+
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), strip_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(strip_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Settings of an Exegol `config.yml` that have an equivalent here
+///
+/// Returns them as a config layer with a description of each imported value.
+pub fn import_exegol(content: &str) -> Result<(Value, Vec<String>), QeError> {
+    let exegol = ConfigFormat::Yaml.parse(content).context("Invalid Exegol config")?;
+
+    let mut layer = Map::new();
+    let mut imported = Vec::new();
+
+    if let Some(auto_update) = exegol.pointer("/config/auto_check_update").and_then(Value::as_bool) {
+        layer.insert("auto_update".to_string(), Value::Bool(auto_update));
+        imported.push(format!("config.auto_check_update → auto_update = {}", auto_update));
+    }
+
+    // Exegol names the shell, we need the binary
+    if let Some(shell) = exegol.pointer("/config/default_start_shell").and_then(Value::as_str) {
+        let shell = if shell.starts_with('/') {
+            shell.to_string()
+        } else {
+            format!("/bin/{}", shell)
+        };
+        imported.push(format!("config.default_start_shell → default_shell = {}", shell));
+        layer.insert("default_shell".to_string(), Value::String(shell));
+    }
+
+    if let Some(workspace) = exegol.pointer("/volumes/private_workspace_path").and_then(Value::as_str) {
+        imported.push(format!("volumes.private_workspace_path → workspace = {}", workspace));
+        layer.insert("workspace".to_string(), Value::String(workspace.to_string()));
+    }
+
+    Ok((Value::Object(layer), imported))
+}
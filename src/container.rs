@@ -2,6 +2,21 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::docker::{self, ChangeKind, ContainerInfo};
+use crate::error::QeError;
+use crate::image::{is_managed_repository, split_reference, MANAGED_LABEL};
+
+/// Paths that churn during normal use and are hidden by `diff --exclude-noise`
+pub const DIFF_NOISE_PATHS: &[&str] = &[
+    "/tmp",
+    "/var/tmp",
+    "/var/cache",
+    "/var/log",
+    "/var/lib/apt/lists",
+    "/root/.cache",
+    "/run",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Container {
     pub id: String,
@@ -30,51 +45,129 @@ pub struct PortMapping {
     pub protocol: String,
 }
 
+/// A path added, changed or deleted in a container since it was created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Added,
+    Changed,
+    Deleted,
+}
+
+/// Path filters applied to a container diff
+#[derive(Debug, Clone, Default)]
+pub struct DiffFilter {
+    /// Only keep paths under one of these prefixes (all paths when empty)
+    pub include: Vec<String>,
+    /// Drop paths under any of these prefixes
+    pub exclude: Vec<String>,
+}
+
+impl DiffFilter {
+    /// Check whether a path passes the filter
+    pub fn matches(&self, path: &str) -> bool {
+        let included = self.include.is_empty()
+            || self.include.iter().any(|p| path_has_prefix(path, p));
+        included && !self.exclude.iter().any(|p| path_has_prefix(path, p))
+    }
+}
+
+/// Prefix match on whole path components, so `/tmp` matches `/tmp/x` but not `/tmpfs`
+fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        return true;
+    }
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+impl From<&ContainerInfo> for Container {
+    fn from(info: &ContainerInfo) -> Self {
+        let status = match info.state.as_str() {
+            "running" => ContainerStatus::Running,
+            "paused" => ContainerStatus::Paused,
+            "restarting" => ContainerStatus::Restarting,
+            "removing" => ContainerStatus::Removing,
+            "exited" => ContainerStatus::Exited,
+            "dead" => ContainerStatus::Dead,
+            // "created" containers have never run
+            _ => ContainerStatus::Stopped,
+        };
+        
+        Self {
+            id: info.id.clone(),
+            name: info
+                .names
+                .first()
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_default(),
+            image: info.image.clone(),
+            status,
+            // Only published ports have a host side
+            ports: info
+                .ports
+                .iter()
+                .filter_map(|p| {
+                    Some(PortMapping {
+                        host_port: p.public_port?,
+                        container_port: p.private_port,
+                        protocol: p.typ.map(|t| t.to_string()).unwrap_or_else(|| "tcp".to_string()),
+                    })
+                })
+                .collect(),
+            created: chrono::DateTime::from_timestamp(info.created, 0)
+                .map(|c| c.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl std::fmt::Display for ContainerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{:?}", self))
+    }
+}
+
+impl std::fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{}->{}/{}", self.host_port, self.container_port, self.protocol))
+    }
+}
+
 pub struct ContainerManager;
 
 impl ContainerManager {
-    /// List all containers (running and stopped)
-    pub fn list() -> Vec<Container> {
-        // Mock implementation - returns demo containers
-        vec![
-            Container {
-                id: "abc123def456".to_string(),
-                name: "quantum-001".to_string(),
-                image: "quantum/security:latest".to_string(),
-                status: ContainerStatus::Running,
-                ports: vec![PortMapping {
-                    host_port: 2222,
-                    container_port: 22,
-                    protocol: "tcp".to_string(),
-                }],
-                created: "2026-02-20T10:00:00Z".to_string(),
-            },
-            Container {
-                id: "def456abc789".to_string(),
-                name: "quantum-002".to_string(),
-                image: "quantum/security:full".to_string(),
-                status: ContainerStatus::Running,
-                ports: vec![PortMapping {
-                    host_port: 3333,
-                    container_port: 3333,
-                    protocol: "tcp".to_string(),
-                }],
-                created: "2026-02-21T14:30:00Z".to_string(),
-            },
-        ]
+    /// List all managed containers (running and stopped)
+    pub fn list() -> Result<Vec<Container>, QeError> {
+        let containers = docker::block_on(docker::list_containers(true))?;
+        Ok(containers
+            .iter()
+            .filter(|c| Self::is_managed(c))
+            .map(Container::from)
+            .collect())
     }
 
     /// List only running containers
-    pub fn list_running() -> Vec<Container> {
-        Self::list()
+    pub fn list_running() -> Result<Vec<Container>, QeError> {
+        Ok(Self::list()?
             .into_iter()
             .filter(|c| matches!(c.status, ContainerStatus::Running))
-            .collect()
+            .collect())
     }
 
     /// Start a container by name
-    pub fn start(name: &str) -> Result<Container, String> {
-        println!("Starting container: {}", name);
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn start(name: &str) -> Result<Container, QeError> {
+        say!("{}", t!("container.starting", name = name));
         // TODO: Implement Docker API call
         Ok(Container {
             id: "new-container-id".to_string(),
@@ -87,29 +180,65 @@ impl ContainerManager {
     }
 
     /// Stop a container by name
-    pub fn stop(name: &str) -> Result<(), String> {
-        println!("Stopping container: {}", name);
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn stop(name: &str) -> Result<(), QeError> {
+        say!("{}", t!("container.stopping", name = name));
         // TODO: Implement Docker API call
         Ok(())
     }
 
     /// Remove a container by name
-    pub fn remove(name: &str) -> Result<(), String> {
-        println!("Removing container: {}", name);
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn remove(name: &str) -> Result<(), QeError> {
+        say!("{}", t!("container.removing", name = name));
         // TODO: Implement Docker API call
         Ok(())
     }
 
     /// Execute a command in a container
-    pub fn exec(name: &str, command: &[String]) -> Result<String, String> {
-        println!("Executing in container {}: {:?}", name, command);
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn exec(name: &str, command: &[String]) -> Result<String, QeError> {
+        say!("{}", t!("container.executing", name = name, command = command.join(" ")));
         // TODO: Implement Docker exec
         Ok("Command output placeholder".to_string())
     }
 
+    /// Check whether a container belongs to the wrapper
+    pub fn is_managed(container: &ContainerInfo) -> bool {
+        container.labels.contains_key(MANAGED_LABEL)
+            || is_managed_repository(&split_reference(&container.image).0)
+    }
+
+    /// Check whether a container is neither running nor about to run
+    pub fn is_stopped(container: &ContainerInfo) -> bool {
+        !matches!(container.state.as_str(), "running" | "paused" | "restarting")
+    }
+
+    /// List filesystem changes of a container against its image
+    pub fn diff(name: &str, filter: &DiffFilter) -> Result<Vec<FileChange>, QeError> {
+        let changes = docker::block_on(docker::container_changes(name))?;
+        
+        let mut changes: Vec<FileChange> = changes
+            .into_iter()
+            .filter(|c| filter.matches(&c.path))
+            .map(|c| FileChange {
+                path: c.path,
+                kind: match c.kind {
+                    ChangeKind::Added => FileChangeKind::Added,
+                    ChangeKind::Modified => FileChangeKind::Changed,
+                    ChangeKind::Deleted => FileChangeKind::Deleted,
+                },
+            })
+            .collect();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        
+        Ok(changes)
+    }
+
     /// Create a new container
-    pub fn create(name: &str, image: &str) -> Result<Container, String> {
-        println!("Creating container {} from image {}", name, image);
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn create(name: &str, image: &str) -> Result<Container, QeError> {
+        say!("{}", t!("container.creating", name = name, image = image));
         // TODO: Implement Docker container creation
         Ok(Container {
             id: format!("{:x}", rand::random::<u128>()),
@@ -121,3 +250,60 @@ impl ContainerManager {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> DiffFilter {
+        DiffFilter {
+            include: include.iter().map(|p| p.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn path_has_prefix_matches_whole_components() {
+        assert!(path_has_prefix("/tmp", "/tmp"));
+        assert!(path_has_prefix("/tmp/x", "/tmp"));
+        assert!(path_has_prefix("/tmp/x", "/tmp/"));
+        assert!(!path_has_prefix("/tmpfs", "/tmp"));
+        assert!(!path_has_prefix("/var", "/var/log"));
+        // An empty or root prefix covers every path
+        assert!(path_has_prefix("/etc/hosts", ""));
+        assert!(path_has_prefix("/etc/hosts", "/"));
+    }
+
+    #[test]
+    fn empty_diff_filter_keeps_every_path() {
+        assert!(DiffFilter::default().matches("/etc/hosts"));
+    }
+
+    #[test]
+    fn diff_filter_keeps_included_paths_only() {
+        let filter = filter(&["/etc", "/opt/tools"], &[]);
+        assert!(filter.matches("/etc/hosts"));
+        assert!(filter.matches("/opt/tools/bin/nmap"));
+        assert!(!filter.matches("/opt/other"));
+        assert!(!filter.matches("/etcetera"));
+    }
+
+    #[test]
+    fn diff_filter_exclusions_win_over_inclusions() {
+        let filter = filter(&["/var"], &["/var/log"]);
+        assert!(filter.matches("/var/lib/dpkg/status"));
+        assert!(!filter.matches("/var/log"));
+        assert!(!filter.matches("/var/log/syslog"));
+        assert!(filter.matches("/var/logs"));
+    }
+
+    #[test]
+    fn noise_paths_hide_churn_but_not_lookalikes() {
+        let filter = filter(&[], DIFF_NOISE_PATHS);
+        assert!(!filter.matches("/tmp/build.log"));
+        assert!(!filter.matches("/root/.cache/pip"));
+        assert!(!filter.matches("/run"));
+        assert!(filter.matches("/root/.bashrc"));
+        assert!(filter.matches("/runner"));
+    }
+}
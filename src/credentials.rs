@@ -0,0 +1,470 @@
+//! Credentials Module - Encrypted registry credential store
+//! Passwords are sealed with AES-256-GCM, keyed by a keyfile or a passphrase
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dialoguer::Password;
+use rand::RngCore;
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::{ConfigManager, CredentialKeySource};
+use crate::error::{QeError, ResultExt};
+use crate::paths;
+use crate::registry::DEFAULT_REGISTRY;
+
+/// Environment variable holding the store passphrase, for non-interactive use
+pub const PASSPHRASE_ENV: &str = "QE_CREDENTIALS_PASSPHRASE";
+
+/// PBKDF2 rounds used to derive the key from a passphrase
+const PBKDF2_ITERATIONS: u32 = 210_000;
+
+/// Known value sealed in the store, decrypting it proves the key is the one the entries use
+const VERIFIER: &[u8] = b"quantum-exegol credential store";
+
+/// Associated data of the verifier, which no registry name can match
+const VERIFIER_AAD: &[u8] = b"quantum-exegol:verifier";
+
+/// Key derived from the passphrase, with the salt it was derived with, so it is asked for once per run
+static PASSPHRASE_KEY: Mutex<Option<(String, Vec<u8>)>> = Mutex::new(None);
+
+/// Username and secret for one registry
+#[derive(Debug, Clone)]
+pub struct Credential {
+    pub username: String,
+    pub password: String,
+}
+
+/// Public part of a stored credential, safe to display
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialInfo {
+    pub registry: String,
+    pub username: String,
+    pub stored_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoreFile {
+    key_source: CredentialKeySource,
+    /// Salt for the passphrase derivation, base64-encoded
+    #[serde(default)]
+    salt: Option<String>,
+    /// `VERIFIER` sealed with the store key, absent from stores written by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verifier: Option<Sealed>,
+    #[serde(default)]
+    entries: BTreeMap<String, StoredEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredEntry {
+    username: String,
+    stored_at: chrono::DateTime<chrono::Utc>,
+    #[serde(flatten)]
+    sealed: Sealed,
+}
+
+/// Value encrypted with the store key, both parts base64-encoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sealed {
+    nonce: String,
+    secret: String,
+}
+
+pub struct CredentialStore {
+    path: PathBuf,
+    file: StoreFile,
+}
+
+impl CredentialStore {
+    /// Open the store in the data directory, creating an empty one if needed
+    pub fn open() -> Result<Self, QeError> {
+        let path = paths::data_dir().join("credentials.json");
+
+        let file = if path.exists() {
+            let content = fs::read_to_string(&path).context("Failed to read credential store")?;
+            serde_json::from_str(&content)
+                .map_err(|e| QeError::ConfigInvalid(format!("Invalid credential store {:?}: {}", path, e)))?
+        } else {
+            let key_source = ConfigManager::load().credential_key;
+            let salt = match key_source {
+                CredentialKeySource::Passphrase => Some(BASE64.encode(random_bytes::<16>())),
+                CredentialKeySource::Keyfile => None,
+            };
+            StoreFile {
+                key_source,
+                salt,
+                verifier: None,
+                entries: BTreeMap::new(),
+            }
+        };
+
+        Ok(Self { path, file })
+    }
+
+    /// List stored credentials without their secrets
+    pub fn list(&self) -> Vec<CredentialInfo> {
+        self.file
+            .entries
+            .iter()
+            .map(|(registry, entry)| CredentialInfo {
+                registry: registry.clone(),
+                username: entry.username.clone(),
+                stored_at: entry.stored_at,
+            })
+            .collect()
+    }
+
+    /// Decrypt the credential of a registry
+    pub fn get(&self, registry: &str) -> Result<Option<Credential>, QeError> {
+        let registry = normalize_registry(registry);
+        let entry = match self.file.entries.get(&registry) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let corrupted = |reason: String| QeError::ConfigInvalid(format!("Corrupted credential for {}: {}", registry, reason));
+        let password = unseal(&self.cipher()?, &entry.sealed, registry.as_bytes())
+            .map_err(corrupted)?
+            .ok_or_else(|| {
+                QeError::PermissionDenied(format!("Cannot decrypt credential for {} (wrong key or passphrase)", registry))
+            })?;
+
+        Ok(Some(Credential {
+            username: entry.username.clone(),
+            password: String::from_utf8(password).map_err(|e| corrupted(e.to_string()))?,
+        }))
+    }
+
+    /// Encrypt and store the credential of a registry
+    pub fn set(&mut self, registry: &str, username: &str, password: &str) -> Result<(), QeError> {
+        let registry = normalize_registry(registry);
+        let cipher = self.cipher()?;
+        if self.file.verifier.is_none() {
+            self.file.verifier = Some(seal(&cipher, VERIFIER, VERIFIER_AAD)?);
+        }
+
+        // The registry name is authenticated so an entry cannot be moved to another registry
+        let sealed = seal(&cipher, password.as_bytes(), registry.as_bytes())?;
+        self.file.entries.insert(
+            registry,
+            StoredEntry {
+                username: username.to_string(),
+                stored_at: chrono::Utc::now(),
+                sealed,
+            },
+        );
+        self.save()
+    }
+
+    /// Forget the credential of a registry, returning whether one was stored
+    pub fn remove(&mut self, registry: &str) -> Result<bool, QeError> {
+        let removed = self.file.entries.remove(&normalize_registry(registry)).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<(), QeError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+
+        let content = serde_json::to_string_pretty(&self.file)
+            .map_err(|e| format!("Failed to serialize credential store: {}", e))?;
+        write_private(&self.path, content.as_bytes())
+    }
+
+    /// Cipher of the store key, refusing a key that is not the one the store was sealed with
+    fn cipher(&self) -> Result<Aes256Gcm, QeError> {
+        let new = |key: &[u8]| {
+            Aes256Gcm::new_from_slice(key).map_err(|_| QeError::ConfigInvalid("Invalid credential key".to_string()))
+        };
+
+        let passphrase = self.file.key_source == CredentialKeySource::Passphrase;
+        let cached = PASSPHRASE_KEY.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some((_, key)) = cached.filter(|(salt, _)| passphrase && self.file.salt.as_ref() == Some(salt)) {
+            return new(&key);
+        }
+
+        let key = match self.file.key_source {
+            CredentialKeySource::Keyfile => self.keyfile()?,
+            CredentialKeySource::Passphrase => self.passphrase_key()?,
+        };
+        let cipher = new(&key)?;
+        self.check_key(&cipher)?;
+
+        if let Some(salt) = self.file.salt.as_ref().filter(|_| passphrase) {
+            *PASSPHRASE_KEY.lock().unwrap_or_else(|e| e.into_inner()) = Some((salt.clone(), key));
+        }
+        Ok(cipher)
+    }
+
+    /// Check a key against the verifier, or against a stored entry in stores without one
+    fn check_key(&self, cipher: &Aes256Gcm) -> Result<(), QeError> {
+        let matches = match &self.file.verifier {
+            Some(verifier) => unseal(cipher, verifier, VERIFIER_AAD).ok().flatten().as_deref() == Some(VERIFIER),
+            None => self.file.entries.iter().next().is_none_or(|(registry, entry)| {
+                unseal(cipher, &entry.sealed, registry.as_bytes()).ok().flatten().is_some()
+            }),
+        };
+        if !matches {
+            let what = match self.file.key_source {
+                CredentialKeySource::Keyfile => "Wrong credential keyfile",
+                CredentialKeySource::Passphrase => "Wrong credential store passphrase",
+            };
+            return Err(QeError::PermissionDenied(format!("{}, the stored credentials were sealed with another key", what)));
+        }
+        Ok(())
+    }
+
+    /// Read the keyfile next to the store, generating it on first use
+    fn keyfile(&self) -> Result<Vec<u8>, QeError> {
+        let path = self.path.with_file_name("credentials.key");
+
+        if path.exists() {
+            let key = fs::read(&path).context(format!("Failed to read keyfile {:?}", path))?;
+            if key.len() != 32 {
+                return Err(QeError::ConfigInvalid(format!("Keyfile {:?} must contain 32 bytes", path)));
+            }
+            return Ok(key);
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let key = random_bytes::<32>().to_vec();
+        write_private(&path, &key)?;
+        Ok(key)
+    }
+
+    /// Derive the key from the passphrase in the environment, or prompt for it
+    fn passphrase_key(&self) -> Result<Vec<u8>, QeError> {
+        let salt = self
+            .file
+            .salt
+            .as_deref()
+            .ok_or_else(|| "Credential store has no passphrase salt".to_string())
+            .and_then(|s| BASE64.decode(s).map_err(|e| format!("Invalid passphrase salt: {}", e)))
+            .map_err(QeError::ConfigInvalid)?;
+
+        let passphrase = match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => passphrase,
+            Err(_) => Password::new()
+                .with_prompt(t!("credentials.passphrase"))
+                .interact()
+                .map_err(|e| QeError::InvalidUsage(format!("Passphrase required (set {}): {}", PASSPHRASE_ENV, e)))?,
+        };
+
+        let mut key = vec![0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations"),
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Ok(key)
+    }
+}
+
+/// Credential stored for a registry, if any
+pub fn lookup(registry: &str) -> Result<Option<Credential>, QeError> {
+    CredentialStore::open()?.get(registry)
+}
+
+/// Map the aliases of Docker Hub to a single registry name
+pub fn normalize_registry(registry: &str) -> String {
+    let registry = registry
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/');
+
+    match registry {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => DEFAULT_REGISTRY.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Encrypt a value with a fresh nonce, authenticating `aad` along with it
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8], aad: &[u8]) -> Result<Sealed, QeError> {
+    let nonce = random_bytes::<12>();
+    let secret = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| QeError::Other("Failed to encrypt credential".to_string()))?;
+    Ok(Sealed {
+        nonce: BASE64.encode(nonce),
+        secret: BASE64.encode(secret),
+    })
+}
+
+/// Decrypt a sealed value, `None` if the key or `aad` do not match and an error if it is malformed
+fn unseal(cipher: &Aes256Gcm, sealed: &Sealed, aad: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let nonce = BASE64.decode(&sealed.nonce).map_err(|e| e.to_string())?;
+    let secret = BASE64.decode(&sealed.secret).map_err(|e| e.to_string())?;
+    // AES-GCM nonces are 96 bits, `Nonce::from_slice` panics on any other length
+    if nonce.len() != 12 {
+        return Err("invalid nonce".to_string());
+    }
+    Ok(cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: &secret, aad }).ok())
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Write a file readable only by the current user where the OS supports it
+fn write_private(path: &PathBuf, content: &[u8]) -> Result<(), QeError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    // Created with the restricted mode so the content is never readable by others, even briefly
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).context(format!("Failed to write {:?}", path))?;
+
+    // The mode only applies to new files, an existing one may have been created wider
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .context(format!("Failed to restrict permissions of {:?}", path))?;
+    }
+
+    file.write_all(content).context(format!("Failed to write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keyfile store in a fresh directory of its own
+    fn store() -> CredentialStore {
+        let name = BASE64.encode(random_bytes::<9>()).replace('/', "_");
+        let dir = std::env::temp_dir().join(format!("qe-credentials-{}", name));
+        fs::create_dir_all(&dir).unwrap();
+        CredentialStore {
+            path: dir.join("credentials.json"),
+            file: StoreFile {
+                key_source: CredentialKeySource::Keyfile,
+                salt: None,
+                verifier: None,
+                entries: BTreeMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn set_then_get_round_trips() {
+        let mut store = store();
+        store.set("https://registry.corp.local/", "alice", "s3cret").unwrap();
+
+        let credential = store.get("registry.corp.local").unwrap().unwrap();
+        assert_eq!(credential.username, "alice");
+        assert_eq!(credential.password, "s3cret");
+        assert!(store.get("other.corp.local").unwrap().is_none());
+
+        // The saved file and keyfile are read back by a new store
+        let content = fs::read_to_string(&store.path).unwrap();
+        let reopened = CredentialStore {
+            path: store.path.clone(),
+            file: serde_json::from_str(&content).unwrap(),
+        };
+        assert_eq!(reopened.get("registry.corp.local").unwrap().unwrap().password, "s3cret");
+    }
+
+    #[test]
+    fn entry_moved_to_another_registry_does_not_decrypt() {
+        let mut store = store();
+        store.set("registry.corp.local", "alice", "s3cret").unwrap();
+
+        let entry = store.file.entries.remove("registry.corp.local").unwrap();
+        store.file.entries.insert("evil.example.com".to_string(), entry);
+        let error = store.get("evil.example.com").unwrap_err();
+        assert!(error.to_string().contains("Cannot decrypt"), "{}", error);
+        assert_eq!(error.exit_code(), 5);
+    }
+
+    #[test]
+    fn malformed_nonce_is_reported_as_corrupted() {
+        let mut store = store();
+        store.set("registry.corp.local", "alice", "s3cret").unwrap();
+
+        store.file.entries.get_mut("registry.corp.local").unwrap().sealed.nonce = BASE64.encode([0u8; 4]);
+        let error = store.get("registry.corp.local").unwrap_err();
+        assert!(error.to_string().contains("Corrupted credential"), "{}", error);
+        assert_eq!(error.exit_code(), 6);
+    }
+
+    #[test]
+    fn another_key_is_refused_before_writing() {
+        let mut store = store();
+        store.set("registry.corp.local", "alice", "s3cret").unwrap();
+        assert!(store.file.verifier.is_some());
+
+        fs::write(store.path.with_file_name("credentials.key"), random_bytes::<32>()).unwrap();
+        let error = store.set("other.corp.local", "bob", "hunter2").unwrap_err();
+        assert_eq!(error.exit_code(), 5, "{}", error);
+        assert!(!store.file.entries.contains_key("other.corp.local"));
+        assert_eq!(store.get("registry.corp.local").unwrap_err().exit_code(), 5);
+    }
+
+    #[test]
+    fn stores_without_verifier_check_the_key_against_an_entry() {
+        let mut store = store();
+        store.set("registry.corp.local", "alice", "s3cret").unwrap();
+        store.file.verifier = None;
+        store.set("other.corp.local", "bob", "hunter2").unwrap();
+        assert!(store.file.verifier.is_some());
+
+        store.file.verifier = None;
+        fs::write(store.path.with_file_name("credentials.key"), random_bytes::<32>()).unwrap();
+        assert!(store.set("third.corp.local", "carol", "pass").is_err());
+    }
+
+    #[test]
+    fn passphrase_key_is_derived_once() {
+        let mut store = store();
+        store.file.key_source = CredentialKeySource::Passphrase;
+        store.file.salt = Some(BASE64.encode(random_bytes::<16>()));
+
+        std::env::set_var(PASSPHRASE_ENV, "correct horse");
+        store.set("registry.corp.local", "alice", "s3cret").unwrap();
+        // A different passphrase is never asked for again in the same run
+        std::env::set_var(PASSPHRASE_ENV, "battery staple");
+        assert_eq!(store.get("registry.corp.local").unwrap().unwrap().password, "s3cret");
+        std::env::remove_var(PASSPHRASE_ENV);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn store_and_keyfile_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut store = store();
+        fs::write(&store.path, "{}").unwrap();
+        fs::set_permissions(&store.path, fs::Permissions::from_mode(0o644)).unwrap();
+        store.set("registry.corp.local", "alice", "s3cret").unwrap();
+
+        for path in [store.path.clone(), store.path.with_file_name("credentials.key")] {
+            let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode, 0o600, "{:?}", path);
+        }
+    }
+}
@@ -2,41 +2,94 @@
 //! This module provides async Docker operations for containers and images
 
 use bollard::container::{
-    ListContainersOptions, LogOutput, LogsOptions,
+    ListContainersOptions, LogOutput,
     RemoveContainerOptions, StartContainerOptions, StopContainerOptions,
 };
-use bollard::image::{CreateImageOptions, ListImagesOptions, RemoveImageOptions};
-use bollard::Docker;
+use bollard::image::{
+    BuildImageOptions, CreateImageOptions, ListImagesOptions, RemoveImageOptions, TagImageOptions,
+};
+use bollard::auth::DockerCredentials;
+use bollard::{Docker, API_DEFAULT_VERSION};
 use bollard::exec::{CreateExecOptions, StartExecResults};
-use bollard::models::Port;
+use bollard::models::{ChangeType, Port};
+use bollard::models::{BuildCache, ContainerSummary, ImageSummary, Volume};
 use futures_util::StreamExt;
+use std::collections::HashMap;
+use log::{debug, warn};
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::runtime::Runtime;
 use tokio::sync::OnceCell;
 
 #[allow(unused_imports)]
 use crate::config::ConfigManager;
+use crate::credentials;
+use crate::error::{QeError, ResultExt};
+use crate::registry::{ImageReference, DEFAULT_REGISTRY};
 
 /// Global Docker connection (singleton)
 static DOCKER: OnceCell<Docker> = OnceCell::const_new();
 
+/// Runtime shared by the synchronous CLI handlers
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Run an async Docker operation to completion from synchronous code
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME
+        .get_or_init(|| Runtime::new().expect("Failed to start async runtime"))
+        .block_on(future)
+}
+
 /// Initialize Docker connection
-pub async fn get_docker() -> Result<&'static Docker, String> {
+pub async fn get_docker() -> Result<&'static Docker, QeError> {
     DOCKER.get_or_try_init(|| async {
-        let config = ConfigManager::load();
+        let socket = ConfigManager::expand("docker_socket", &ConfigManager::load().docker_socket)?;
         
         // Connect to Docker daemon
-        let docker = Docker::connect_with_local_defaults()
-            .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+        let docker = Docker::connect_with_socket(&socket, 120, API_DEFAULT_VERSION)
+            .map_err(|e| connection_error(e, &socket))?;
         
         // Verify connection
-        docker.ping().await
-            .map_err(|e| format!("Docker ping failed: {}", e))?;
+        timed(format_args!("ping {}", socket), docker.ping()).await
+            .map_err(|e| connection_error(e, &socket))?;
         
         Ok(docker)
     }).await
 }
 
+/// Await a Docker API call, logging it with its duration
+async fn timed<T>(
+    call: impl fmt::Display,
+    future: impl Future<Output = Result<T, bollard::errors::Error>>,
+) -> Result<T, bollard::errors::Error> {
+    let started = Instant::now();
+    let result = future.await;
+    log_call(&call, started, result.as_ref().err().map(|e| e as &dyn fmt::Display));
+    result
+}
+
+fn log_call(call: &dyn fmt::Display, started: Instant, error: Option<&dyn fmt::Display>) {
+    let elapsed = started.elapsed().as_millis();
+    match error {
+        None => debug!("Docker API {} ({} ms)", call, elapsed),
+        Some(e) => debug!("Docker API {} failed after {} ms: {}", call, elapsed, e),
+    }
+}
+
+/// Any failure to talk to the daemon means it is unreachable, unless access was denied
+fn connection_error(error: bollard::errors::Error, socket: &str) -> QeError {
+    let error = match QeError::from(error) {
+        error if matches!(error.root(), QeError::PermissionDenied(_) | QeError::DockerUnreachable(_)) => error,
+        error => QeError::DockerUnreachable(error.to_string()),
+    };
+    error.context(format!("Cannot connect to Docker at {}", socket))
+}
+
 /// List all containers (including stopped)
-pub async fn list_containers(all: bool) -> Result<Vec<ContainerInfo>, String> {
+pub async fn list_containers(all: bool) -> Result<Vec<ContainerInfo>, QeError> {
     let docker = get_docker().await?;
     
     let options = ListContainersOptions::<String> {
@@ -44,22 +97,15 @@ pub async fn list_containers(all: bool) -> Result<Vec<ContainerInfo>, String> {
         ..Default::default()
     };
     
-    let containers = docker.list_containers(Some(options))
+    let containers = timed(format_args!("list_containers all={}", all), docker.list_containers(Some(options)))
         .await
-        .map_err(|e| format!("Failed to list containers: {}", e))?;
-    
-    Ok(containers.into_iter().map(|c| ContainerInfo {
-        id: c.id.unwrap_or_default(),
-        names: c.names.unwrap_or_default(),
-        image: c.image.unwrap_or_default(),
-        status: c.status.unwrap_or_default(),
-        state: c.state.unwrap_or_default(),
-        ports: c.ports.unwrap_or_default(),
-    }).collect())
+        .context("Failed to list containers")?;
+    
+    Ok(containers.into_iter().map(ContainerInfo::from).collect())
 }
 
 /// List all images
-pub async fn list_images() -> Result<Vec<ImageInfo>, String> {
+pub async fn list_images() -> Result<Vec<ImageInfo>, QeError> {
     let docker = get_docker().await?;
     
     let options = ListImagesOptions::<String> {
@@ -67,127 +113,279 @@ pub async fn list_images() -> Result<Vec<ImageInfo>, String> {
         ..Default::default()
     };
     
-    let images = docker.list_images(Some(options))
+    let images = timed("list_images", docker.list_images(Some(options)))
+        .await
+        .context("Failed to list images")?;
+    
+    Ok(images.into_iter().map(ImageInfo::from).collect())
+}
+
+/// Get disk usage of images, containers, volumes and build cache
+pub async fn disk_usage() -> Result<DiskUsageInfo, QeError> {
+    let docker = get_docker().await?;
+    
+    let usage = timed("df", docker.df())
+        .await
+        .context("Failed to get disk usage")?;
+    
+    Ok(DiskUsageInfo {
+        images: usage.images.unwrap_or_default().into_iter().map(ImageInfo::from).collect(),
+        containers: usage.containers.unwrap_or_default().into_iter().map(ContainerInfo::from).collect(),
+        volumes: usage.volumes.unwrap_or_default().into_iter().map(VolumeInfo::from).collect(),
+        build_cache: usage.build_cache.unwrap_or_default().into_iter().map(BuildCacheInfo::from).collect(),
+    })
+}
+
+/// Inspect a local image
+pub async fn inspect_image(name: &str) -> Result<ImageDetails, QeError> {
+    let docker = get_docker().await?;
+    
+    let image = timed(format_args!("inspect_image {}", name), docker.inspect_image(name))
+        .await
+        .context("Failed to inspect image")?;
+    let config = image.config.unwrap_or_default();
+    
+    Ok(ImageDetails {
+        id: image.id.unwrap_or_default(),
+        repo_tags: image.repo_tags.unwrap_or_default(),
+        repo_digests: image.repo_digests.unwrap_or_default(),
+        created: image.created.unwrap_or_default(),
+        architecture: image.architecture.unwrap_or_default(),
+        variant: image.variant,
+        os: image.os.unwrap_or_default(),
+        size: image.size.unwrap_or_default(),
+        layers: image.root_fs.and_then(|r| r.layers).unwrap_or_default(),
+        entrypoint: config.entrypoint.unwrap_or_default(),
+        cmd: config.cmd.unwrap_or_default(),
+        env: config.env.unwrap_or_default(),
+        labels: config.labels.unwrap_or_default(),
+        working_dir: config.working_dir.unwrap_or_default(),
+        user: config.user.unwrap_or_default(),
+    })
+}
+
+/// Get the build history of a local image, newest entry first
+pub async fn image_history(name: &str) -> Result<Vec<HistoryInfo>, QeError> {
+    let docker = get_docker().await?;
+    
+    let history = timed(format_args!("image_history {}", name), docker.image_history(name))
         .await
-        .map_err(|e| format!("Failed to list images: {}", e))?;
+        .context("Failed to get image history")?;
     
-    Ok(images.into_iter().map(|i| ImageInfo {
-        id: i.id,
-        repo_tags: i.repo_tags,
-        size: i.size,
-        created: i.created,
+    Ok(history.into_iter().map(|h| HistoryInfo {
+        created: h.created,
+        created_by: h.created_by,
+        size: h.size,
     }).collect())
 }
 
-/// Pull an image from registry
-pub async fn pull_image(name: &str, tag: &str) -> Result<(), String> {
+/// Pull an image from registry, optionally for a given platform such as `linux/arm64`
+pub async fn pull_image(name: &str, tag: &str, platform: Option<&str>) -> Result<(), QeError> {
     let docker = get_docker().await?;
     
     let options = CreateImageOptions {
         from_image: name,
         tag,
+        platform: platform.unwrap_or_default(),
         ..Default::default()
     };
     
-    let mut stream = docker.create_image(Some(options), None, None);
+    // Private registries need the credentials stored by `login`
+    let registry = ImageReference::parse(name)?.registry;
+    // An unreadable store is not a reason to fail a pull that may not need it
+    let stored = credentials::lookup(&registry).unwrap_or_else(|e| {
+        warn!("Ignoring stored credentials for {}: {}", registry, e);
+        None
+    });
+    let credentials = stored.map(|c| DockerCredentials {
+        username: Some(c.username),
+        password: Some(c.password),
+        serveraddress: Some(if registry == DEFAULT_REGISTRY {
+            // The engine still identifies Docker Hub by its v1 index address
+            "https://index.docker.io/v1/".to_string()
+        } else {
+            registry
+        }),
+        ..Default::default()
+    });
+    
+    let call = format!("create_image {}:{} platform={}", name, tag, platform.unwrap_or("host"));
+    let started = Instant::now();
+    let mut stream = docker.create_image(Some(options), None, credentials);
     
     while let Some(result) = stream.next().await {
         match result {
             Ok(info) => {
                 if let Some(status) = info.status {
-                    println!("  • {}", status);
+                    say!("  • {}", status);
+                }
+            }
+            Err(e) => {
+                log_call(&call, started, Some(&e));
+                return Err(QeError::from(e).context("Failed to pull image"));
+            }
+        }
+    }
+    
+    log_call(&call, started, None);
+    Ok(())
+}
+
+/// Tag an image under another repository and tag
+pub async fn tag_image(source: &str, repository: &str, tag: &str) -> Result<(), QeError> {
+    let docker = get_docker().await?;
+    
+    let options = TagImageOptions { repo: repository, tag };
+    timed(format_args!("tag_image {} {}:{}", source, repository, tag), docker.tag_image(source, Some(options)))
+        .await
+        .context("Failed to tag image")?;
+    
+    Ok(())
+}
+
+/// Build an image from a context directory, optionally for a given platform
+pub async fn build_image(
+    context: &Path,
+    dockerfile: &str,
+    tag: &str,
+    platform: Option<&str>,
+    labels: HashMap<String, String>,
+) -> Result<(), QeError> {
+    let docker = get_docker().await?;
+    
+    // The engine expects the build context as a tar archive
+    let mut archive = tar::Builder::new(Vec::new());
+    archive.append_dir_all(".", context)
+        .context("Failed to archive build context")?;
+    let archive = archive.into_inner()
+        .context("Failed to archive build context")?;
+    
+    let options = BuildImageOptions {
+        dockerfile: dockerfile.to_string(),
+        t: tag.to_string(),
+        platform: platform.unwrap_or_default().to_string(),
+        labels,
+        rm: true,
+        ..Default::default()
+    };
+    
+    let call = format!("build_image {} ({} bytes of context)", tag, archive.len());
+    let started = Instant::now();
+    let mut stream = docker.build_image(options, None, Some(archive.into()));
+    
+    while let Some(result) = stream.next().await {
+        match result {
+            Ok(info) => {
+                // The API call succeeds and reports a failed build in the stream
+                if let Some(error) = info.error {
+                    log_call(&call, started, Some(&error));
+                    return Err(QeError::Other(format!("Build failed: {}", error)));
                 }
+                // Build steps are progress for people, kept off stdout when it carries records
+                if let Some(output) = info.stream {
+                    say!("{}", output.trim_end_matches('\n'));
+                }
+            }
+            Err(e) => {
+                log_call(&call, started, Some(&e));
+                return Err(QeError::from(e).context("Failed to build image"));
             }
-            Err(e) => return Err(format!("Failed to pull image: {}", e)),
         }
     }
     
+    log_call(&call, started, None);
     Ok(())
 }
 
 /// Start a container
-pub async fn start_container(name: &str) -> Result<(), String> {
+#[allow(dead_code)] // Not reachable from the CLI yet
+pub async fn start_container(name: &str) -> Result<(), QeError> {
     let docker = get_docker().await?;
     
-    docker.start_container(name, None::<StartContainerOptions<String>>)
+    timed(format_args!("start_container {}", name), docker.start_container(name, None::<StartContainerOptions<String>>))
         .await
-        .map_err(|e| format!("Failed to start container: {}", e))?;
+        .context("Failed to start container")?;
     
     Ok(())
 }
 
 /// Stop a container
-pub async fn stop_container(name: &str) -> Result<(), String> {
+#[allow(dead_code)] // Not reachable from the CLI yet
+pub async fn stop_container(name: &str) -> Result<(), QeError> {
     let docker = get_docker().await?;
     
-    docker.stop_container(name, Some(StopContainerOptions {
+    timed(format_args!("stop_container {}", name), docker.stop_container(name, Some(StopContainerOptions {
         t: 10,
-    }))
+    })))
     .await
-    .map_err(|e| format!("Failed to stop container: {}", e))?;
+    .context("Failed to stop container")?;
     
     Ok(())
 }
 
 /// Restart a container
-pub async fn restart_container(name: &str) -> Result<(), String> {
+#[allow(dead_code)] // Not reachable from the CLI yet
+pub async fn restart_container(name: &str) -> Result<(), QeError> {
     let docker = get_docker().await?;
     
-    docker.restart_container(name, Some(bollard::container::RestartContainerOptions {
+    timed(format_args!("restart_container {}", name), docker.restart_container(name, Some(bollard::container::RestartContainerOptions {
         t: 10,
-    }))
+    })))
     .await
-    .map_err(|e| format!("Failed to restart container: {}", e))?;
+    .context("Failed to restart container")?;
     
     Ok(())
 }
 
 /// Remove a container
-pub async fn remove_container(name: &str, force: bool) -> Result<(), String> {
+pub async fn remove_container(name: &str, force: bool) -> Result<(), QeError> {
     let docker = get_docker().await?;
     
-    docker.remove_container(name, Some(RemoveContainerOptions {
+    timed(format_args!("remove_container {} force={}", name, force), docker.remove_container(name, Some(RemoveContainerOptions {
         force,
         ..Default::default()
-    }))
+    })))
     .await
-    .map_err(|e| format!("Failed to remove container: {}", e))?;
+    .context("Failed to remove container")?;
     
     Ok(())
 }
 
-/// Remove an image
-pub async fn remove_image(name: &str, force: bool) -> Result<(), String> {
+/// Remove an image or one of its tags, returning whether the image itself was deleted
+///
+/// Without `force`, removing a tag only untags an image that has other tags.
+pub async fn remove_image(name: &str, force: bool) -> Result<bool, QeError> {
     let docker = get_docker().await?;
     
-    docker.remove_image(name, Some(RemoveImageOptions {
+    let removed = timed(format_args!("remove_image {} force={}", name, force), docker.remove_image(name, Some(RemoveImageOptions {
         force,
         ..Default::default()
-    }), None)
+    }), None))
     .await
-    .map_err(|e| format!("Failed to remove image: {}", e))?;
+    .context("Failed to remove image")?;
     
-    Ok(())
+    Ok(removed.iter().any(|item| item.deleted.is_some()))
 }
 
 /// Execute command in container
-pub async fn exec_in_container(name: &str, cmd: &[String]) -> Result<String, String> {
+#[allow(dead_code)] // Not reachable from the CLI yet
+pub async fn exec_in_container(name: &str, cmd: &[String]) -> Result<String, QeError> {
     let docker = get_docker().await?;
     
     // Create exec instance
-    let exec = docker.create_exec(name, CreateExecOptions {
+    let exec = timed(format_args!("create_exec {} {:?}", name, cmd), docker.create_exec(name, CreateExecOptions {
         cmd: Some(cmd.to_vec()),
         attach_stdout: Some(true),
         attach_stderr: Some(true),
         ..Default::default()
-    })
+    }))
     .await
-    .map_err(|e| format!("Failed to create exec: {}", e))?;
+    .context("Failed to create exec")?;
     
     // Start exec and get output
-    let output = docker.start_exec(&exec.id, None)
+    let output = timed(format_args!("start_exec {}", exec.id), docker.start_exec(&exec.id, None))
         .await
-        .map_err(|e| format!("Failed to start exec: {}", e))?;
+        .context("Failed to start exec")?;
     
     let mut output_string = String::new();
     
@@ -208,39 +406,34 @@ pub async fn exec_in_container(name: &str, cmd: &[String]) -> Result<String, Str
         StartExecResults::Detached => {}
     }
     
+    let code = timed(format_args!("inspect_exec {}", exec.id), docker.inspect_exec(&exec.id))
+        .await
+        .context("Failed to inspect exec")?
+        .exit_code
+        .unwrap_or_default();
+    if code != 0 {
+        return Err(QeError::CommandFailed { command: cmd.join(" "), code });
+    }
+    
     Ok(output_string)
 }
 
-/// Get container logs
-pub async fn get_container_logs(name: &str, tail: usize) -> Result<String, String> {
+/// List filesystem changes of a container against its image
+pub async fn container_changes(name: &str) -> Result<Vec<ChangeInfo>, QeError> {
     let docker = get_docker().await?;
     
-    let options = LogsOptions::<String> {
-        stdout: true,
-        stderr: true,
-        tail: tail.to_string(),
-        ..Default::default()
-    };
-    
-    let mut logs = docker.logs(name, Some(options));
-    
-    let mut output = String::new();
-    while let Some(log) = logs.next().await {
-        match log {
-            Ok(msg) => output.push_str(&msg.to_string()),
-            Err(e) => return Err(format!("Failed to read logs: {}", e)),
-        }
-    }
+    let changes = timed(format_args!("container_changes {}", name), docker.container_changes(name))
+        .await
+        .context("Failed to get container changes")?;
     
-    Ok(output)
-}
-
-/// Check if Docker is available
-pub async fn check_docker() -> Result<bool, String> {
-    match get_docker().await {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
-    }
+    Ok(changes.unwrap_or_default().into_iter().map(|c| ChangeInfo {
+        path: c.path,
+        kind: match c.kind {
+            ChangeType::_0 => ChangeKind::Modified,
+            ChangeType::_1 => ChangeKind::Added,
+            ChangeType::_2 => ChangeKind::Deleted,
+        },
+    }).collect())
 }
 
 // Data structures for container and image info
@@ -250,17 +443,139 @@ pub struct ContainerInfo {
     pub id: String,
     pub names: Vec<String>,
     pub image: String,
-    pub status: String,
     pub state: String,
     pub ports: Vec<Port>,
+    pub created: i64,
+    pub labels: HashMap<String, String>,
+    /// Size of the writable layer (only reported by `disk_usage`)
+    pub size_rw: i64,
+}
+
+impl From<ContainerSummary> for ContainerInfo {
+    fn from(c: ContainerSummary) -> Self {
+        Self {
+            id: c.id.unwrap_or_default(),
+            names: c.names.unwrap_or_default(),
+            image: c.image.unwrap_or_default(),
+            state: c.state.unwrap_or_default(),
+            ports: c.ports.unwrap_or_default(),
+            created: c.created.unwrap_or_default(),
+            labels: c.labels.unwrap_or_default(),
+            size_rw: c.size_rw.unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ImageInfo {
     pub id: String,
     pub repo_tags: Vec<String>,
+    pub repo_digests: Vec<String>,
+    pub size: i64,
+    pub shared_size: i64,
+    pub created: i64,
+    pub labels: HashMap<String, String>,
+    /// Number of containers using the image (-1 when not computed by the engine)
+    pub containers: i64,
+}
+
+impl From<ImageSummary> for ImageInfo {
+    fn from(i: ImageSummary) -> Self {
+        Self {
+            id: i.id,
+            repo_tags: i.repo_tags,
+            repo_digests: i.repo_digests,
+            size: i.size,
+            shared_size: i.shared_size,
+            created: i.created,
+            labels: i.labels,
+            containers: i.containers,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageDetails {
+    pub id: String,
+    pub repo_tags: Vec<String>,
+    pub repo_digests: Vec<String>,
+    pub created: String,
+    pub architecture: String,
+    pub variant: Option<String>,
+    pub os: String,
     pub size: i64,
+    /// Uncompressed layer digests (diff ids), base layer first
+    pub layers: Vec<String>,
+    pub entrypoint: Vec<String>,
+    pub cmd: Vec<String>,
+    pub env: Vec<String>,
+    pub labels: HashMap<String, String>,
+    pub working_dir: String,
+    pub user: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryInfo {
     pub created: i64,
+    pub created_by: String,
+    pub size: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VolumeInfo {
+    pub labels: HashMap<String, String>,
+    pub size: i64,
+    pub ref_count: i64,
+}
+
+impl From<Volume> for VolumeInfo {
+    fn from(v: Volume) -> Self {
+        let usage = v.usage_data.unwrap_or_default();
+        Self {
+            labels: v.labels,
+            size: usage.size,
+            ref_count: usage.ref_count,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildCacheInfo {
+    pub size: i64,
+    pub in_use: bool,
+    pub shared: bool,
+}
+
+impl From<BuildCache> for BuildCacheInfo {
+    fn from(b: BuildCache) -> Self {
+        Self {
+            size: b.size.unwrap_or_default(),
+            in_use: b.in_use.unwrap_or_default(),
+            shared: b.shared.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiskUsageInfo {
+    pub images: Vec<ImageInfo>,
+    pub containers: Vec<ContainerInfo>,
+    pub volumes: Vec<VolumeInfo>,
+    pub build_cache: Vec<BuildCacheInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangeInfo {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+/// Kind of filesystem change, as reported by the engine changes API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Modified,
+    Added,
+    Deleted,
 }
 
 /// Format size to human readable
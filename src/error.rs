@@ -0,0 +1,219 @@
+//! Error Module - Failures reported by the CLI, each with a stable exit code
+//! Scripts may rely on the exit codes below, the messages can change between releases
+//!
+//! | Code | Failure                                      |
+//! |------|----------------------------------------------|
+//! | 1    | Any other failure                            |
+//! | 2    | Invalid command line, or missing value       |
+//! | 3    | Container, image, profile or key not found   |
+//! | 4    | Conflict, e.g. the name is already in use    |
+//! | 5    | Permission denied                            |
+//! | 6    | Invalid configuration                        |
+//! | 7    | Docker daemon unreachable                    |
+//! | 8    | Network or registry failure                  |
+//! | 9    | Command failed inside the container          |
+//! | 130  | Cancelled by the user                        |
+
+use std::backtrace::Backtrace;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Record backtraces, set by `--debug`
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug)]
+pub enum QeError {
+    DockerUnreachable(String),
+    NotFound(String),
+    Conflict(String),
+    PermissionDenied(String),
+    ConfigInvalid(String),
+    /// A required value is missing and cannot be asked for, like clap's own usage errors
+    InvalidUsage(String),
+    /// Registry, catalog or any other remote service
+    Network(String),
+    Cancelled,
+    CommandFailed { command: String, code: i64 },
+    Other(String),
+    /// What was being done when the inner error happened
+    Context { message: String, source: Box<QeError> },
+    /// Where the inner error was raised, only recorded with `--debug`
+    Traced { source: Box<QeError>, backtrace: Box<Backtrace> },
+}
+
+impl QeError {
+    /// Record backtraces from now on
+    pub fn set_debug(debug: bool) {
+        DEBUG.store(debug, Ordering::Relaxed);
+    }
+
+    /// Exit code of the process for this error, see the table above
+    pub fn exit_code(&self) -> i32 {
+        match self.root() {
+            QeError::Other(_) => 1,
+            QeError::InvalidUsage(_) => 2,
+            QeError::NotFound(_) => 3,
+            QeError::Conflict(_) => 4,
+            QeError::PermissionDenied(_) => 5,
+            QeError::ConfigInvalid(_) => 6,
+            QeError::DockerUnreachable(_) => 7,
+            QeError::Network(_) => 8,
+            QeError::CommandFailed { .. } => 9,
+            QeError::Cancelled => 130,
+            QeError::Context { .. } | QeError::Traced { .. } => unreachable!("root() skips wrappers"),
+        }
+    }
+
+    /// The error that started it all, without context or backtrace
+    pub fn root(&self) -> &QeError {
+        match self {
+            QeError::Context { source, .. } | QeError::Traced { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Say what was being done, keeping the kind of the error
+    pub fn context(self, message: impl Into<String>) -> Self {
+        QeError::Context {
+            message: message.into(),
+            source: Box::new(self.traced()),
+        }
+    }
+
+    /// Messages from the outermost context to the root error
+    pub fn chain(&self) -> Vec<String> {
+        match self {
+            QeError::Context { message, source } => {
+                let mut chain = vec![message.clone()];
+                chain.extend(source.chain());
+                chain
+            }
+            QeError::Traced { source, .. } => source.chain(),
+            root => vec![root.message()],
+        }
+    }
+
+    /// Backtrace recorded with `--debug`
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            QeError::Traced { backtrace, .. } => Some(backtrace),
+            QeError::Context { source, .. } => source.backtrace(),
+            _ => None,
+        }
+    }
+
+    /// Attach a backtrace of the current call stack under `--debug`, once
+    fn traced(self) -> Self {
+        if !DEBUG.load(Ordering::Relaxed) || self.backtrace().is_some() {
+            return self;
+        }
+        QeError::Traced {
+            source: Box::new(self),
+            backtrace: Box::new(Backtrace::force_capture()),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            QeError::DockerUnreachable(message)
+            | QeError::NotFound(message)
+            | QeError::Conflict(message)
+            | QeError::PermissionDenied(message)
+            | QeError::ConfigInvalid(message)
+            | QeError::InvalidUsage(message)
+            | QeError::Network(message)
+            | QeError::Other(message) => message.clone(),
+            QeError::Cancelled => "Cancelled".to_string(),
+            QeError::CommandFailed { command, code } => {
+                format!("Command '{}' exited with code {}", command, code)
+            }
+            QeError::Context { message, .. } => message.clone(),
+            QeError::Traced { source, .. } => source.message(),
+        }
+    }
+}
+
+impl fmt::Display for QeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.chain().join(": "))
+    }
+}
+
+impl std::error::Error for QeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QeError::Context { source, .. } => Some(source.as_ref()),
+            QeError::Traced { source, .. } => source.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Add context to the error of a `Result`
+pub trait ResultExt<T> {
+    fn context(self, message: impl Into<String>) -> Result<T, QeError>;
+}
+
+impl<T, E: Into<QeError>> ResultExt<T> for Result<T, E> {
+    fn context(self, message: impl Into<String>) -> Result<T, QeError> {
+        self.map_err(|e| e.into().context(message))
+    }
+}
+
+impl From<String> for QeError {
+    fn from(message: String) -> Self {
+        QeError::Other(message).traced()
+    }
+}
+
+impl From<&str> for QeError {
+    fn from(message: &str) -> Self {
+        QeError::Other(message.to_string()).traced()
+    }
+}
+
+impl From<bollard::errors::Error> for QeError {
+    fn from(error: bollard::errors::Error) -> Self {
+        use bollard::errors::Error;
+
+        let message = error.to_string();
+        let error = match &error {
+            Error::DockerResponseServerError { status_code, message } => match status_code {
+                404 => QeError::NotFound(message.clone()),
+                409 => QeError::Conflict(message.clone()),
+                401 | 403 => QeError::PermissionDenied(message.clone()),
+                _ => QeError::Other(message.clone()),
+            },
+            // A socket the user may not open is the usual reason for a refused connection
+            Error::IOError { err } if err.kind() == std::io::ErrorKind::PermissionDenied => {
+                QeError::PermissionDenied(message)
+            }
+            Error::IOError { .. }
+            | Error::HyperLegacyError { .. }
+            | Error::HyperResponseError { .. }
+            | Error::RequestTimeoutError
+            | Error::UnsupportedURISchemeError { .. } => QeError::DockerUnreachable(message),
+            _ => QeError::Other(message),
+        };
+        error.traced()
+    }
+}
+
+impl From<reqwest::Error> for QeError {
+    fn from(error: reqwest::Error) -> Self {
+        QeError::Network(error.to_string()).traced()
+    }
+}
+
+impl From<std::io::Error> for QeError {
+    fn from(error: std::io::Error) -> Self {
+        let message = error.to_string();
+        let error = match error.kind() {
+            std::io::ErrorKind::NotFound => QeError::NotFound(message),
+            std::io::ErrorKind::PermissionDenied => QeError::PermissionDenied(message),
+            std::io::ErrorKind::Interrupted => QeError::Cancelled,
+            _ => QeError::Other(message),
+        };
+        error.traced()
+    }
+}
@@ -0,0 +1,175 @@
+//! I18n Module - French and English catalogs of the messages shown to people
+//! The language comes from the `language` config key, or from `LC_ALL`, `LC_MESSAGES` and `LANG` when it is `auto`
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::config::{ConfigManager, Language};
+
+/// Catalogs embedded in the binary, English first as the fallback
+const CATALOGS: &[(Lang, &str)] = &[
+    (Lang::En, include_str!("../locales/en.toml")),
+    (Lang::Fr, include_str!("../locales/fr.toml")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lang {
+    En,
+    Fr,
+}
+
+impl Lang {
+    /// Language of a locale such as `fr_FR.UTF-8`, English for anything unknown
+    pub fn from_locale(locale: &str) -> Self {
+        match locale.split(['_', '.', '@']).next() {
+            Some("fr") => Lang::Fr,
+            _ => Lang::En,
+        }
+    }
+
+    /// Language of the environment, following the POSIX precedence of locale variables
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map(|locale| Lang::from_locale(&locale))
+            .unwrap_or(Lang::En)
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+static MESSAGES: OnceLock<HashMap<Lang, HashMap<String, String>>> = OnceLock::new();
+
+/// Language of this run, read from the config on first use
+pub fn lang() -> Lang {
+    *LANG.get_or_init(|| match ConfigManager::load().language {
+        Language::Auto => Lang::from_env(),
+        Language::En => Lang::En,
+        Language::Fr => Lang::Fr,
+    })
+}
+
+fn messages() -> &'static HashMap<Lang, HashMap<String, String>> {
+    MESSAGES.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(lang, source)| {
+                let table: toml::Table = toml::from_str(source).expect("embedded catalogs are valid TOML");
+                let mut messages = HashMap::new();
+                flatten("", table, &mut messages);
+                (*lang, messages)
+            })
+            .collect()
+    })
+}
+
+/// `[install] title = "..."` becomes `install.title`
+fn flatten(prefix: &str, table: toml::Table, messages: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, messages),
+            toml::Value::String(message) => {
+                messages.insert(key, message);
+            }
+            other => {
+                messages.insert(key, other.to_string());
+            }
+        }
+    }
+}
+
+/// Message for a key in the current language, `{name}` placeholders replaced by the arguments
+///
+/// Falls back to English, then to the key itself, so a missing translation never hides a message.
+pub fn tr(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let messages = messages();
+    let mut message = [lang(), Lang::En]
+        .iter()
+        .find_map(|lang| messages.get(lang).and_then(|m| m.get(key)))
+        .cloned()
+        .unwrap_or_else(|| key.to_string());
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    message
+}
+
+/// Translate a catalog key: `t!("start.title")` or `t!("common.container", name = container_name)`
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::tr($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr($key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(message: &str) -> BTreeSet<&str> {
+        message
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn every_key_exists_in_every_catalog() {
+        let messages = messages();
+        let keys: BTreeSet<&String> = messages.values().flat_map(|m| m.keys()).collect();
+        for (lang, catalog) in messages {
+            let missing: Vec<_> = keys.iter().filter(|key| !catalog.contains_key(**key)).collect();
+            assert!(missing.is_empty(), "{:?} catalog is missing {:?}", lang, missing);
+        }
+    }
+
+    #[test]
+    fn translations_use_the_same_placeholders() {
+        let messages = messages();
+        let english = &messages[&Lang::En];
+        for (lang, catalog) in messages {
+            for (key, message) in catalog {
+                assert_eq!(
+                    placeholders(message),
+                    placeholders(&english[key]),
+                    "{:?} translation of {} has other placeholders",
+                    lang,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_key_used_in_the_sources_exists() {
+        let english = &messages()[&Lang::En];
+        let sources = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+        for entry in sources {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            for (start, _) in source.match_indices("t!(\"") {
+                // Skip `format!(` and other macros ending in `t`
+                if source[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let key = source[start + 4..].split('"').next().unwrap();
+                assert!(english.contains_key(key), "{} uses unknown key {}", path.display(), key);
+            }
+        }
+    }
+
+    #[test]
+    fn locale_selects_the_language() {
+        assert_eq!(Lang::from_locale("fr_FR.UTF-8"), Lang::Fr);
+        assert_eq!(Lang::from_locale("fr"), Lang::Fr);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+    }
+}
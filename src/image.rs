@@ -1,6 +1,20 @@
 //! Image Manager Module - Docker image operations
 
+use colored::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::docker::{self, ImageInfo};
+use crate::error::QeError;
+use crate::registry::{self, ImageReference, ManifestResponse, RegistryClient, RemoteManifest};
+use crate::signature::{self, Verification};
+
+/// Label set on images and containers created by the wrapper
+pub const MANAGED_LABEL: &str = "io.quantum-exegol.managed";
+
+/// Repository namespace of the wrapper's images
+pub const MANAGED_NAMESPACE: &str = "quantum";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
@@ -10,17 +24,40 @@ pub struct Image {
     pub size: u64,
     pub created: String,
     pub digest: Option<String>,
+    /// Image has lost all its tags
+    #[serde(default)]
+    pub dangling: bool,
+    /// Operating system and architecture the image was built for
+    #[serde(default)]
+    pub platform: Option<Platform>,
+}
+
+impl Image {
+    /// Full `repository:tag` reference
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.repository, self.tag)
+    }
+
+    /// Image id without the algorithm prefix, truncated like the docker CLI
+    pub fn short_id(&self) -> &str {
+        let id = self.id.trim_start_matches("sha256:");
+        &id[..id.len().min(12)]
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ImageManifest {
     pub schema_version: u32,
+    /// Optional in OCI manifests, where the Content-Type header carries it instead
+    #[serde(default)]
     pub media_type: String,
     pub config: ManifestConfig,
     pub layers: Vec<ManifestLayer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ManifestConfig {
     pub media_type: String,
     pub size: u64,
@@ -28,103 +65,554 @@ pub struct ManifestConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ManifestLayer {
     pub media_type: String,
     pub size: u64,
     pub digest: String,
 }
 
+/// Manifest list (OCI image index) pointing at one manifest per platform
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestList {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub media_type: String,
+    pub manifests: Vec<ManifestDescriptor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestDescriptor {
+    pub media_type: String,
+    pub size: u64,
+    pub digest: String,
+    #[serde(default)]
+    pub platform: Option<Platform>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platform {
+    pub architecture: String,
+    pub os: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    /// Parse a platform such as `linux/arm64` or `linux/arm/v7`
+    pub fn parse(value: &str) -> Result<Self, QeError> {
+        let parts: Vec<&str> = value.split('/').collect();
+        match parts.as_slice() {
+            [os, architecture] | [os, architecture, _] if !os.is_empty() && !architecture.is_empty() => Ok(Self {
+                os: os.to_string(),
+                architecture: architecture.to_string(),
+                variant: parts.get(2).map(|v| v.to_string()),
+            }),
+            _ => Err(QeError::InvalidUsage(format!(
+                "Invalid platform '{}' (expected os/arch, e.g. linux/arm64)",
+                value
+            ))),
+        }
+    }
+
+    /// Check whether images for this platform run natively on the host, without emulation
+    pub fn is_native(&self) -> bool {
+        let host = Self::host();
+        self.os == host.os && self.architecture == host.architecture
+    }
+
+    /// Check whether a manifest list entry targets this platform
+    pub fn matches(&self, other: &Platform) -> bool {
+        self.os == other.os
+            && self.architecture == other.architecture
+            && (self.variant.is_none() || other.variant.is_none() || self.variant == other.variant)
+    }
+
+    /// Platform of the machine running the wrapper
+    pub fn host() -> Self {
+        let architecture = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            "x86" => "386",
+            "arm" => "arm",
+            other => other,
+        };
+        Self {
+            architecture: architecture.to_string(),
+            os: "linux".to_string(),
+            variant: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.variant {
+            Some(variant) => write!(f, "{}/{}/{}", self.os, self.architecture, variant),
+            None => write!(f, "{}/{}", self.os, self.architecture),
+        }
+    }
+}
+
+/// Result of comparing a local image with its registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatus {
+    pub reference: String,
+    pub local_digest: Option<String>,
+    pub remote_digest: Option<String>,
+    pub outdated: bool,
+    /// Compressed size of the new image's layers, an upper bound on the download
+    pub download_size: u64,
+    /// Platform of the installed image, kept when pulling the update
+    pub platform: Option<Platform>,
+    /// Why the image could not be checked
+    pub error: Option<String>,
+}
+
+/// One entry of an image's build history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerHistory {
+    /// Instruction that created the entry, e.g. `RUN apt-get install ...`
+    pub instruction: String,
+    /// Uncompressed layer digest, `None` for metadata-only instructions
+    pub digest: Option<String>,
+    pub size: u64,
+    pub created: String,
+}
+
+/// Detailed view of a local image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageInspection {
+    pub image: Image,
+    pub manifest: ImageManifest,
+    pub history: Vec<LayerHistory>,
+    pub architecture: String,
+    pub os: String,
+    pub entrypoint: Vec<String>,
+    pub cmd: Vec<String>,
+    pub env: Vec<String>,
+    pub labels: HashMap<String, String>,
+    pub working_dir: String,
+    pub user: String,
+    /// Total size of the image
+    pub total_size: u64,
+    /// Part of the total size in layers shared with other local images
+    pub shared_size: u64,
+}
+
+/// Media types used for manifests built from the local engine
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.docker.container.image.v1+json";
+const LAYER_MEDIA_TYPE: &str = "application/vnd.docker.image.rootfs.diff.tar";
+
+/// Dockerfile instructions that only change image metadata and never create a layer
+const METADATA_INSTRUCTIONS: &[&str] = &[
+    "ARG", "CMD", "ENTRYPOINT", "ENV", "EXPOSE", "HEALTHCHECK", "LABEL", "MAINTAINER",
+    "ONBUILD", "SHELL", "STOPSIGNAL", "USER", "VOLUME", "WORKDIR",
+];
+
 pub struct ImageManager;
 
 impl ImageManager {
-    /// List all available images
-    pub fn list() -> Vec<Image> {
-        // Mock implementation - returns demo images
-        vec![
-            Image {
-                id: "sha256:abc123".to_string(),
-                repository: "quantum/security".to_string(),
-                tag: "latest".to_string(),
-                size: 2_300_000_000, // ~2.3 GB
-                created: "2026-02-15T08:00:00Z".to_string(),
-                digest: Some("sha256:abc123def456...".to_string()),
-            },
-            Image {
-                id: "sha256:def456".to_string(),
-                repository: "quantum/security".to_string(),
-                tag: "full".to_string(),
-                size: 4_100_000_000, // ~4.1 GB
-                created: "2026-02-10T12:00:00Z".to_string(),
-                digest: Some("sha256:def456ghi789...".to_string()),
-            },
-            Image {
-                id: "sha256:ghi789".to_string(),
-                repository: "quantum/security".to_string(),
-                tag: "light".to_string(),
-                size: 1_200_000_000, // ~1.2 GB
-                created: "2026-02-18T16:00:00Z".to_string(),
-                digest: Some("sha256:ghi789jkl012...".to_string()),
-            },
-            Image {
-                id: "sha256:jkl012".to_string(),
-                repository: "quantum/pentest".to_string(),
-                tag: "latest".to_string(),
-                size: 3_500_000_000, // ~3.5 GB
-                created: "2026-02-12T10:00:00Z".to_string(),
-                digest: Some("sha256:jkl012mno345...".to_string()),
-            },
-        ]
-    }
-
-    /// Pull an image from registry
-    pub fn pull(repository: &str, tag: &str) -> Result<Image, String> {
-        println!("Pulling image: {}:{}", repository, tag);
-        // TODO: Implement Docker pull
-        Ok(Image {
-            id: format!("sha256:{:x}", rand::random::<u128>()),
-            repository: repository.to_string(),
-            tag: tag.to_string(),
-            size: 2_000_000_000,
-            created: chrono::Utc::now().to_rfc3339(),
-            digest: None,
+    /// List all local images, one entry per repository tag, without their platform
+    pub fn list() -> Result<Vec<Image>, QeError> {
+        let infos = docker::block_on(docker::list_images())?;
+        
+        let mut images: Vec<Image> = infos.iter().flat_map(Self::from_info).collect();
+        images.sort_by(|a, b| (&a.repository, &a.tag).cmp(&(&b.repository, &b.tag)));
+        
+        Ok(images)
+    }
+
+    /// Fill in the platform of listed images
+    ///
+    /// The list endpoint does not report architectures, so each engine image is inspected once.
+    pub fn add_platforms(images: &mut [Image]) {
+        let mut platforms: HashMap<String, Option<Platform>> = HashMap::new();
+        for image in images {
+            image.platform = platforms
+                .entry(image.id.clone())
+                .or_insert_with(|| {
+                    docker::block_on(docker::inspect_image(&image.id))
+                        .ok()
+                        .map(|d| Platform {
+                            architecture: d.architecture,
+                            os: d.os,
+                            variant: d.variant,
+                        })
+                })
+                .clone();
+        }
+    }
+
+    /// Build the models for each tag of an engine image (a single untagged one if dangling)
+    fn from_info(info: &ImageInfo) -> Vec<Image> {
+        let created = chrono::DateTime::from_timestamp(info.created, 0)
+            .unwrap_or_default()
+            .to_rfc3339();
+        let digest_for = |repository: &str| {
+            info.repo_digests
+                .iter()
+                .map(|d| split_reference(d))
+                .find(|(repo, _)| same_repository(repo, repository) || repository == "<none>")
+                .map(|(_, digest)| digest)
+        };
+        let image = |repository: String, tag: String| Image {
+            id: info.id.clone(),
+            digest: digest_for(&repository),
+            repository,
+            tag,
+            size: info.size.max(0) as u64,
+            created: created.clone(),
+            dangling: Self::is_dangling(info),
+            platform: None,
+        };
+        
+        if Self::is_dangling(info) {
+            return vec![image("<none>".to_string(), "<none>".to_string())];
+        }
+        
+        info.repo_tags
+            .iter()
+            .map(|r| {
+                let (repository, tag) = split_reference(r);
+                image(repository, tag)
+            })
+            .collect()
+    }
+
+    /// Pull an image from registry, optionally for another platform than the host's
+    ///
+    /// Mirrors and rewrite rules from the config are tried in order, and the image is
+    /// tagged under the requested name whichever endpoint served it. With a `digest`, that
+    /// manifest is pulled rather than whatever the tag points to, so a verified image cannot
+    /// be swapped between the check and the pull.
+    pub fn pull(repository: &str, tag: &str, digest: Option<&str>, platform: Option<&Platform>) -> Result<Image, QeError> {
+        let platform = platform.map(|p| p.to_string());
+        let requested = match digest {
+            Some(digest) => format!("{}@{}", repository, digest),
+            None => format!("{}:{}", repository, tag),
+        };
+        let mut errors = Vec::new();
+        let mut last = None;
+        
+        for source in registry::pull_sources(&requested)? {
+            let name = format!("{}/{}", source.registry, source.repository);
+            log::info!("Pulling {} from {}", requested, source.registry);
+            match docker::block_on(docker::pull_image(&name, &source.reference, platform.as_deref())) {
+                Ok(()) => {
+                    say!("  {}", t!("install.served_by", registry = source.registry));
+                    // A digest pull leaves the image untagged
+                    if digest.is_some() || ImageReference::parse(&requested)? != source {
+                        docker::block_on(docker::tag_image(&source.to_string(), repository, tag))?;
+                    }
+                    return Self::info(repository, tag);
+                }
+                Err(e) => {
+                    say!("  {} {}: {}", "⚠".yellow(), source.registry, e);
+                    if let Some((registry, previous)) = last.replace((source.registry.clone(), e)) {
+                        errors.push(format!("{}: {}", registry, previous));
+                    }
+                }
+            }
+        }
+        
+        Err(pull_error(last, &errors, format!("Failed to pull {}", requested)))
+    }
+
+    /// Platforms offered by a remote image (empty for a single-platform manifest)
+    pub fn remote_platforms(reference: &str) -> Result<Vec<Platform>, QeError> {
+        let (_, response) = Self::remote_manifest(reference)?;
+        Ok(match response.manifest {
+            RemoteManifest::List(list) => list
+                .manifests
+                .into_iter()
+                .filter_map(|m| m.platform)
+                // Attestation manifests are listed with an `unknown` platform
+                .filter(|p| p.os != "unknown")
+                .collect(),
+            RemoteManifest::Image(_) => Vec::new(),
         })
     }
 
+    /// Verify the signature of a remote image before pulling it
+    pub fn verify_remote(reference: &str) -> Result<Verification, QeError> {
+        let (parsed, response) = Self::remote_manifest(reference)?;
+        signature::verify(&parsed, &response.digest)
+    }
+
     /// Remove an image
-    pub fn remove(repository: &str, tag: &str) -> Result<(), String> {
-        println!("Removing image: {}:{}", repository, tag);
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn remove(repository: &str, tag: &str) -> Result<(), QeError> {
+        say!("{}", t!("container.removing_image", name = format!("{}:{}", repository, tag)));
         // TODO: Implement Docker rmi
         Ok(())
     }
 
-    /// Build an image from Dockerfile
-    pub fn build(dockerfile: &str, tag: &str) -> Result<Image, String> {
-        println!("Building image from: {}", dockerfile);
-        // TODO: Implement Docker build
-        Ok(Image {
-            id: format!("sha256:{:x}", rand::random::<u128>()),
-            repository: "quantum/custom".to_string(),
-            tag: tag.to_string(),
-            size: 1_500_000_000,
-            created: chrono::Utc::now().to_rfc3339(),
-            digest: None,
-        })
+    /// Build an image from Dockerfile, using its directory as build context
+    pub fn build(dockerfile: &str, tag: &str, platform: Option<&Platform>) -> Result<Image, QeError> {
+        let path = Path::new(dockerfile);
+        let context = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let file_name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .ok_or_else(|| format!("Invalid Dockerfile path: {}", dockerfile))?;
+        
+        let labels = HashMap::from([(MANAGED_LABEL.to_string(), "true".to_string())]);
+        let platform = platform.map(|p| p.to_string());
+        docker::block_on(docker::build_image(context, &file_name, tag, platform.as_deref(), labels))?;
+        
+        let (repository, tag) = split_reference(tag);
+        Self::info(&repository, &tag)
     }
 
-    /// Get image info
-    pub fn info(repository: &str, tag: &str) -> Result<Image, String> {
-        println!("Getting info for image: {}:{}", repository, tag);
-        // TODO: Implement Docker inspect
-        Ok(Image {
-            id: format!("sha256:{:x}", rand::random::<u128>()),
-            repository: repository.to_string(),
-            tag: tag.to_string(),
-            size: 2_000_000_000,
-            created: chrono::Utc::now().to_rfc3339(),
-            digest: None,
+    /// Get image info, from the engine inspect alone
+    pub fn info(repository: &str, tag: &str) -> Result<Image, QeError> {
+        let reference = format!("{}:{}", repository, tag);
+        let details = docker::block_on(docker::inspect_image(&reference))?;
+        Ok(Self::image_from_details(&details, &reference))
+    }
+
+    /// Summary of an inspected image, named after `reference` when it is one of its tags
+    fn image_from_details(details: &docker::ImageDetails, reference: &str) -> Image {
+        let (repository, tag) = details
+            .repo_tags
+            .iter()
+            .find(|r| split_reference(r) == split_reference(reference))
+            .or(details.repo_tags.first())
+            .map(|r| split_reference(r))
+            .unwrap_or_else(|| ("<none>".to_string(), "<none>".to_string()));
+        let digest = details
+            .repo_digests
+            .iter()
+            .map(|d| split_reference(d))
+            .find(|(repo, _)| same_repository(repo, &repository))
+            .map(|(_, digest)| digest);
+        
+        Image {
+            id: details.id.clone(),
+            repository,
+            tag,
+            size: details.size.max(0) as u64,
+            created: details.created.clone(),
+            digest,
+            dangling: details.repo_tags.is_empty(),
+            platform: Some(Platform {
+                architecture: details.architecture.clone(),
+                os: details.os.clone(),
+                variant: details.variant.clone(),
+            }),
+        }
+    }
+
+    /// Inspect a local image, rebuilding its manifest from the engine inspect and history
+    ///
+    /// Slower than `info`: the shared size needs the full disk usage report.
+    pub fn inspect(reference: &str) -> Result<ImageInspection, QeError> {
+        let details = docker::block_on(docker::inspect_image(reference))?;
+        let history = docker::block_on(docker::image_history(reference))?;
+        
+        // The engine only computes shared sizes as part of the disk usage report
+        let shared_size = docker::block_on(docker::disk_usage())?
+            .images
+            .into_iter()
+            .find(|i| i.id == details.id)
+            .map_or(0, |i| i.shared_size.max(0) as u64);
+        
+        let history = Self::layer_history(&details.layers, history);
+        let layers = history
+            .iter()
+            .filter_map(|h| {
+                h.digest.as_ref().map(|digest| ManifestLayer {
+                    media_type: LAYER_MEDIA_TYPE.to_string(),
+                    size: h.size,
+                    digest: digest.clone(),
+                })
+            })
+            .collect();
+        
+        Ok(ImageInspection {
+            image: Self::image_from_details(&details, reference),
+            manifest: ImageManifest {
+                schema_version: 2,
+                media_type: MANIFEST_MEDIA_TYPE.to_string(),
+                config: ManifestConfig {
+                    media_type: CONFIG_MEDIA_TYPE.to_string(),
+                    // The engine does not expose the raw config blob, so its size is unknown
+                    size: 0,
+                    digest: details.id,
+                },
+                layers,
+            },
+            history,
+            architecture: match details.variant {
+                Some(variant) => format!("{}/{}", details.architecture, variant),
+                None => details.architecture,
+            },
+            os: details.os,
+            entrypoint: details.entrypoint,
+            cmd: details.cmd,
+            env: details.env,
+            labels: details.labels,
+            working_dir: details.working_dir,
+            user: details.user,
+            total_size: details.size.max(0) as u64,
+            shared_size,
         })
     }
 
+    /// Match history entries (newest first) to the image layers (base first)
+    fn layer_history(layers: &[String], history: Vec<docker::HistoryInfo>) -> Vec<LayerHistory> {
+        let history: Vec<_> = history.into_iter().rev().collect();
+        
+        // An entry creates a layer unless it is a metadata instruction with no content
+        let creates_layer = |h: &docker::HistoryInfo| {
+            let instruction = format_instruction(&h.created_by);
+            let keyword = instruction.split_whitespace().next().unwrap_or_default();
+            h.size > 0 || !METADATA_INSTRUCTIONS.contains(&keyword)
+        };
+        let mut creating: Vec<bool> = history.iter().map(creates_layer).collect();
+        if creating.iter().filter(|c| **c).count() != layers.len() {
+            creating = history.iter().map(|h| h.size > 0).collect();
+        }
+        // Without a consistent mapping, digests are left out rather than guessed
+        let mapped = creating.iter().filter(|c| **c).count() == layers.len();
+        
+        let mut layers = layers.iter();
+        history
+            .iter()
+            .zip(creating)
+            .map(|(h, creates)| LayerHistory {
+                instruction: format_instruction(&h.created_by),
+                digest: if creates && mapped { layers.next().cloned() } else { None },
+                size: h.size.max(0) as u64,
+                created: chrono::DateTime::from_timestamp(h.created, 0)
+                    .unwrap_or_default()
+                    .to_rfc3339(),
+            })
+            .collect()
+    }
+
+    /// List the tags of a repository on its registry
+    pub fn remote_tags(repository: &str) -> Result<Vec<String>, QeError> {
+        let reference = ImageReference::parse(repository)?;
+        let mut client = RegistryClient::for_registry(&reference.registry);
+        docker::block_on(client.list_tags(&reference.repository))
+    }
+
+    /// Fetch the manifest (or manifest list) of a remote image without pulling it
+    ///
+    /// Goes through the same mirrors and rewrite rules as a pull and returns the source that answered.
+    pub fn remote_manifest(reference: &str) -> Result<(ImageReference, ManifestResponse), QeError> {
+        let mut errors = Vec::new();
+        let mut last = None;
+        
+        for source in registry::pull_sources(reference)? {
+            let mut client = RegistryClient::for_registry(&source.registry);
+            match docker::block_on(client.get_manifest(&source.repository, &source.reference)) {
+                Ok(manifest) => return Ok((source, manifest)),
+                Err(e) => {
+                    log::debug!("Manifest of {} not served by {}: {}", reference, source.registry, e);
+                    if let Some((registry, previous)) = last.replace((source.registry.clone(), e)) {
+                        errors.push(format!("{}: {}", registry, previous));
+                    }
+                }
+            }
+        }
+        
+        Err(pull_error(last, &errors, format!("Failed to fetch manifest of {}", reference)))
+    }
+
+    /// Compare installed managed images with their registry, optionally only one reference
+    pub fn check_updates(only: Option<&str>) -> Result<Vec<UpdateStatus>, QeError> {
+        let mut images: Vec<Image> = Self::list()?
+            .into_iter()
+            .filter(|i| !i.dangling && is_managed_repository(&i.repository))
+            .filter(|i| only.is_none_or(|r| i.reference() == r || i.repository == r))
+            .collect();
+        // Updates resolve manifest lists to the platform installed
+        Self::add_platforms(&mut images);
+        
+        Ok(images.iter().map(Self::check_update).collect())
+    }
+
+    fn check_update(image: &Image) -> UpdateStatus {
+        let mut status = UpdateStatus {
+            reference: image.reference(),
+            local_digest: image.digest.clone(),
+            remote_digest: None,
+            outdated: false,
+            download_size: 0,
+            platform: image.platform.clone(),
+            error: None,
+        };
+        
+        if image.digest.is_none() {
+            status.error = Some("built locally, no registry digest".to_string());
+            return status;
+        }
+        
+        let result = Self::remote_manifest(&status.reference).and_then(|(source, response)| {
+            let mut client = RegistryClient::for_registry(&source.registry);
+            let platform = status.platform.clone().unwrap_or_else(Platform::host);
+            let size = docker::block_on(Self::download_size(&mut client, &source.repository, &response, &platform))?;
+            Ok((response.digest, size))
+        });
+        
+        match result {
+            Ok((digest, size)) => {
+                status.outdated = image.digest.as_deref() != Some(digest.as_str());
+                status.download_size = if status.outdated { size } else { 0 };
+                status.remote_digest = Some(digest);
+            }
+            Err(e) => status.error = Some(e.to_string()),
+        }
+        status
+    }
+
+    /// Total layer size of a manifest, resolving lists to the given platform
+    async fn download_size(
+        client: &mut RegistryClient,
+        repository: &str,
+        response: &ManifestResponse,
+        platform: &Platform,
+    ) -> Result<u64, QeError> {
+        let manifest = match &response.manifest {
+            RemoteManifest::Image(manifest) => manifest.clone(),
+            RemoteManifest::List(list) => {
+                let entry = list
+                    .manifests
+                    .iter()
+                    .find(|m| m.platform.as_ref().is_some_and(|p| platform.matches(p)))
+                    .ok_or_else(|| format!("No {} image in manifest list", platform))?;
+                match client.get_manifest(repository, &entry.digest).await?.manifest {
+                    RemoteManifest::Image(manifest) => manifest,
+                    RemoteManifest::List(_) => return Err(QeError::Network("Nested manifest list".to_string())),
+                }
+            }
+        };
+        
+        Ok(manifest.layers.iter().map(|l| l.size).sum())
+    }
+
+    /// Check whether an image belongs to the wrapper
+    pub fn is_managed(image: &ImageInfo) -> bool {
+        image.labels.contains_key(MANAGED_LABEL)
+            || image.repo_tags.iter().any(|r| is_managed_repository(&split_reference(r).0))
+            // Dangling images lose their tags but keep the repository digest
+            || image.repo_digests.iter().any(|r| is_managed_repository(&split_reference(r).0))
+    }
+
+    /// Check whether an image has no tag left
+    pub fn is_dangling(image: &ImageInfo) -> bool {
+        image.repo_tags.iter().all(|r| r == "<none>:<none>")
+    }
+
     /// Format size to human readable string
     pub fn format_size(bytes: u64) -> String {
         const KB: u64 = 1024;
@@ -142,3 +630,60 @@ impl ImageManager {
         }
     }
 }
+
+/// Split an image reference into repository and tag (or digest)
+///
+/// `registry:5000/quantum/security:full` gives `("registry:5000/quantum/security", "full")`,
+/// and a reference without tag defaults to `latest`.
+pub fn split_reference(reference: &str) -> (String, String) {
+    if let Some((repository, digest)) = reference.split_once('@') {
+        return (repository.to_string(), digest.to_string());
+    }
+    
+    match reference.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository.to_string(), tag.to_string()),
+        _ => (reference.to_string(), "latest".to_string()),
+    }
+}
+
+/// Check whether a repository lives in the wrapper's namespace, on any registry
+pub fn is_managed_repository(repository: &str) -> bool {
+    repository
+        .split('/')
+        .rev()
+        .nth(1)
+        .is_some_and(|namespace| namespace == MANAGED_NAMESPACE)
+}
+
+/// Turn a history `created_by` into the Dockerfile instruction it came from
+pub fn format_instruction(created_by: &str) -> String {
+    let created_by = created_by.trim();
+    let instruction = if let Some(rest) = created_by.strip_prefix("/bin/sh -c #(nop)") {
+        rest.trim().to_string()
+    } else if let Some(rest) = created_by.strip_prefix("/bin/sh -c") {
+        format!("RUN {}", rest.trim())
+    } else {
+        created_by.to_string()
+    };
+    
+    // BuildKit appends a marker to the instructions it records
+    instruction.trim_end_matches("# buildkit").trim().to_string()
+}
+
+/// Check whether two names refer to the same repository, ignoring the registry one of them
+/// was pulled through (an image pulled from a mirror keeps the mirror's name in its digests)
+pub fn same_repository(a: &str, b: &str) -> bool {
+    a == b || a.ends_with(&format!("/{}", b)) || b.ends_with(&format!("/{}", a))
+}
+
+/// Error of the last source tried, which is the registry itself, naming the mirrors that failed before it
+fn pull_error(last: Option<(String, QeError)>, mirrors: &[String], message: String) -> QeError {
+    let message = if mirrors.is_empty() {
+        message
+    } else {
+        format!("{} (mirrors failed: {})", message, mirrors.join("; "))
+    };
+    last.map(|(_, error)| error)
+        .unwrap_or_else(|| QeError::NotFound("No registry to pull from".to_string()))
+        .context(message)
+}
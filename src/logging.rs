@@ -0,0 +1,154 @@
+//! Logging Module - `-v` levels on stderr and a rotating log file in the logs directory
+//! The file keeps the debug records of this crate, including every Docker API call, at any verbosity
+
+use colored::*;
+use log::{warn, Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::paths;
+
+/// Current log file, older ones get a `.1` to `.5` suffix
+pub const LOG_FILE: &str = "quantum-exegol.log";
+
+/// Size above which the log file is rotated at startup
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Rotated log files kept
+const KEEP_LOGS: usize = 5;
+
+/// Records of this crate written to the file whatever the verbosity
+const FILE_LEVEL: Level = Level::Debug;
+
+/// Log file, opened once the logger is installed
+static FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+struct Logger {
+    stderr: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata) || (FILE.get().is_some() && in_file(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+
+        if let Some(file) = FILE.get().filter(|_| in_file(record.metadata())) {
+            let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+            // Losing a log line is better than failing the command
+            writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+            .ok();
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(file) = FILE.get() {
+            file.lock().unwrap_or_else(|e| e.into_inner()).flush().ok();
+        }
+    }
+}
+
+/// Warnings from anywhere, debug records from this crate only
+fn in_file(metadata: &Metadata) -> bool {
+    metadata.level() <= Level::Warn
+        || (metadata.level() <= FILE_LEVEL && metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+}
+
+/// Level shown on stderr for a number of `-v` flags
+pub fn level(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Install the logger, `RUST_LOG` overriding the level given by `-v`
+pub fn init(verbose: u8) {
+    let stderr = env_logger::Builder::new()
+        .filter_level(level(verbose))
+        .parse_env("RUST_LOG")
+        .format(move |buf, record| {
+            let level = match record.level() {
+                Level::Error => t!("common.error").red(),
+                Level::Warn => t!("common.warning").yellow(),
+                other => other.to_string().dimmed(),
+            };
+            if verbose == 0 {
+                writeln!(buf, "{}: {}", level, record.args())
+            } else {
+                writeln!(
+                    buf,
+                    "{} {} {}: {}",
+                    chrono::Local::now().format("%H:%M:%S%.3f").to_string().dimmed(),
+                    level,
+                    record.target().dimmed(),
+                    record.args()
+                )
+            }
+        })
+        .build();
+
+    // Installed before the file is opened: finding the logs directory loads the config,
+    // which reports its warnings through the logger
+    let stderr_level = stderr.filter();
+    if log::set_boxed_logger(Box::new(Logger { stderr })).is_err() {
+        return;
+    }
+    log::set_max_level(stderr_level);
+
+    match open_log_file() {
+        Ok(file) => {
+            FILE.set(Mutex::new(file)).ok();
+            log::set_max_level(stderr_level.max(FILE_LEVEL.to_level_filter()));
+        }
+        Err(e) => warn!("{}", t!("log.file_disabled", error = e)),
+    }
+}
+
+/// Path of the current log file
+pub fn log_file_path() -> PathBuf {
+    paths::logs_dir().join(LOG_FILE)
+}
+
+fn open_log_file() -> Result<File, String> {
+    let path = log_file_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create logs directory: {}", e))?;
+    }
+
+    let too_big = fs::metadata(&path).map(|m| m.len() > MAX_LOG_SIZE).unwrap_or(false);
+    if too_big {
+        rotate(&path);
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))
+}
+
+/// Shift `file.N` to `file.N+1`, dropping the oldest, then move the file to `file.1`
+fn rotate(path: &Path) {
+    let numbered = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    for n in (1..KEEP_LOGS).rev() {
+        fs::rename(numbered(n), numbered(n + 1)).ok();
+    }
+    fs::rename(path, numbered(1)).ok();
+}
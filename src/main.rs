@@ -1,17 +1,32 @@
 //! Quantum Exegol - Environmental Cybersecurity Framework
 //! A Rust-based alternative to Exegol for offensive security operations
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use colored::*;
 use std::process::exit;
 
+#[macro_use]
+mod output;
+#[macro_use]
+mod i18n;
+
+mod catalog;
 mod cli;
+mod completion;
 mod container;
 mod image;
 mod config;
+mod config_format;
+mod credentials;
+mod error;
+mod logging;
 mod manager;
+mod paths;
+mod profile;
 mod utils;
 mod docker;
+mod registry;
+mod signature;
 
 #[derive(Parser)]
 #[command(name = "quantum-exegol")]
@@ -20,8 +35,33 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    #[arg(short, long, default_value = "false")]
-    verbose: bool,
+    /// More details on stderr: -v info, -vv debug, -vvv trace
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Override a config value for this run, e.g. --set auto_update=false
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
+
+    /// Output mode: table, json, yaml or ids
+    #[arg(short, long, global = true, default_value = "table")]
+    output: String,
+
+    /// Template applied to each record, e.g. '{{.name}} {{.status}}'
+    #[arg(long, global = true)]
+    format: Option<String>,
+
+    /// Only print identifiers and requested values
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Disable colours
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Show the cause of errors and where they were raised
+    #[arg(long, global = true)]
+    debug: bool,
 }
 
 #[derive(Subcommand)]
@@ -31,8 +71,20 @@ enum Commands {
         #[arg(short, long)]
         name: Option<String>,
 
+        /// Flavour from the catalog (see `images --available`)
+        #[arg(short, long, conflicts_with_all = ["name", "tag"])]
+        flavour: Option<String>,
+
         #[arg(short, long)]
         tag: Option<String>,
+
+        /// Platform to pull, e.g. linux/arm64 or linux/amd64 (defaults to the host's)
+        #[arg(long)]
+        platform: Option<String>,
+
+        /// Skip image signature verification
+        #[arg(long)]
+        insecure: bool,
     },
 
     /// Start a container with selected environment
@@ -42,6 +94,10 @@ enum Commands {
 
         #[arg(short, long)]
         image: Option<String>,
+
+        /// Skip image signature verification
+        #[arg(long)]
+        insecure: bool,
     },
 
     /// Stop a running container
@@ -60,34 +116,116 @@ enum Commands {
     },
 
     /// List available images
-    Images,
+    Images {
+        #[command(subcommand)]
+        action: Option<ImagesCommand>,
+
+        /// List flavours available for installation instead of local images
+        #[arg(long)]
+        available: bool,
+
+        /// Fetch the flavour catalog again instead of using the cache
+        #[arg(long, requires = "available")]
+        refresh: bool,
+    },
 
     /// List running containers
     Ps,
 
+    /// Show filesystem changes of a container against its image
+    Diff {
+        /// Container name or id
+        container: String,
+
+        /// Only show paths under this prefix (repeatable)
+        #[arg(short, long = "path")]
+        paths: Vec<String>,
+
+        /// Hide paths under this prefix (repeatable)
+        #[arg(short, long)]
+        exclude: Vec<String>,
+
+        /// Hide noisy paths such as /tmp and /var/cache
+        #[arg(long)]
+        exclude_noise: bool,
+    },
+
+    /// Show disk space used by managed images, containers, volumes and build cache
+    Df,
+
+    /// Remove unused managed containers and images
+    Prune {
+        /// Remove stopped containers
+        #[arg(long)]
+        containers: bool,
+
+        /// Remove unused images
+        #[arg(long)]
+        images: bool,
+
+        /// Remove dangling images
+        #[arg(long)]
+        dangling: bool,
+
+        /// Only remove resources older than this (e.g. 12h, 7d, 2w)
+        #[arg(long, value_name = "DURATION")]
+        older_than: Option<String>,
+
+        /// Keep the N most recent images of each repository
+        #[arg(long, value_name = "N")]
+        keep_last: Option<usize>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
     /// Remove a container
     Remove {
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
     },
 
     /// Update images and wrapper
     Update {
         #[arg(short, long)]
         image: Option<String>,
+
+        /// Only report outdated images, without pulling
+        #[arg(long)]
+        check: bool,
+
+        /// Skip image signature verification
+        #[arg(long)]
+        insecure: bool,
     },
 
     /// Build custom image
     Build {
         #[arg(short, long)]
         dockerfile: Option<String>,
+
+        /// Tag of the built image
+        #[arg(short, long)]
+        tag: Option<String>,
+
+        /// Platform to build for, e.g. linux/arm64 or linux/amd64 (defaults to the host's)
+        #[arg(long)]
+        platform: Option<String>,
     },
 
     /// Display version information
     Version,
 
     /// Configure Quantum Exegol
-    Config,
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommand>,
+    },
 
     /// Restart a container
     Restart {
@@ -99,6 +237,41 @@ enum Commands {
     Uninstall {
         #[arg(short, long)]
         name: Option<String>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Manage container profiles
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+
+    /// Store credentials for a registry
+    Login {
+        /// Registry host, e.g. registry.corp.local (defaults to Docker Hub)
+        registry: Option<String>,
+
+        #[arg(short, long)]
+        username: Option<String>,
+
+        /// Read the password from stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+
+    /// Forget the credentials of a registry
+    Logout {
+        /// Registry host (defaults to Docker Hub)
+        registry: Option<String>,
+    },
+
+    /// Manage stored registry credentials
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsCommand,
     },
 
     /// Activate license
@@ -106,77 +279,316 @@ enum Commands {
         #[arg(short, long)]
         key: Option<String>,
     },
+
+    /// Print the completion script of a shell, e.g. `source <(quantum-exegol completions bash)`
+    Completions {
+        shell: completion::CompletionShell,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImagesCommand {
+    /// Show manifest, layers and history of a local image
+    Inspect {
+        /// Image reference (repository:tag or id)
+        reference: String,
+    },
+
+    /// List the tags of a repository on its registry
+    RemoteTags {
+        /// Repository, e.g. quantum/security or registry.corp.local/quantum/security
+        repository: String,
+    },
+
+    /// Show the manifest of a remote image without pulling it
+    RemoteInspect {
+        /// Image reference (repository:tag or repository@digest)
+        reference: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Show the effective configuration
+    Show {
+        /// Show which layer (default, system, user, project, env, cli) set each value
+        #[arg(long)]
+        origin: bool,
+    },
+
+    /// Print the effective value of a key
+    Get {
+        /// Config key, e.g. default_image or registries
+        key: String,
+    },
+
+    /// Set a value in the user config file
+    Set {
+        key: String,
+        value: String,
+    },
+
+    /// Remove a value from the user config file, falling back to the lower layers
+    Unset {
+        key: String,
+    },
+
+    /// Open the user config file in $EDITOR and check it before saving
+    Edit,
+
+    /// Remove every setting from the user config file
+    Reset {
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Check every config file, QE_* variable and --set flag
+    Validate,
+
+    /// Print the path of the user config file
+    Path {
+        /// Also print the data, cache, state, logs and workspace directories
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Write the JSON Schema of the config next to the config file, for editors
+    Schema {
+        /// Print the schema instead of writing it
+        #[arg(long)]
+        stdout: bool,
+    },
+
+    /// Rewrite the user config file in another format
+    Convert {
+        /// Target format: json, toml or yaml
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Import compatible settings from an Exegol config.yml
+    Import {
+        /// Exegol config file (defaults to ~/.exegol/config.yml)
+        path: Option<String>,
+    },
+}
+
+// Parsed once per run, boxing `Create` would only add noise
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// List the profiles defined in the configuration
+    List,
+
+    /// Show a profile with the values it inherits
+    Show {
+        name: String,
+    },
+
+    /// Add a profile to the user config file
+    Create {
+        name: String,
+
+        /// Profile to inherit unset values from
+        #[arg(long)]
+        extends: Option<String>,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        #[arg(short, long)]
+        image: Option<String>,
+
+        /// Docker network mode, e.g. host or bridge
+        #[arg(long)]
+        network: Option<String>,
+
+        /// Bind mount as host:container[:ro] (repeatable)
+        #[arg(short, long = "mount")]
+        mounts: Vec<String>,
+
+        /// Published port as [ip:]host:container[/proto] (repeatable)
+        #[arg(short, long = "port")]
+        ports: Vec<String>,
+
+        /// Environment variable as KEY=VALUE (repeatable)
+        #[arg(short, long = "env")]
+        env: Vec<String>,
+
+        /// Linux capability to add, e.g. NET_ADMIN (repeatable)
+        #[arg(long = "cap-add")]
+        capabilities: Vec<String>,
+
+        /// Memory limit, e.g. 4g
+        #[arg(long)]
+        memory: Option<String>,
+
+        /// CPU limit, e.g. 2 or 1.5
+        #[arg(long)]
+        cpus: Option<f64>,
+
+        #[arg(long)]
+        shell: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CredentialsCommand {
+    /// List registries with stored credentials (secrets are never shown)
+    List,
 }
 
 fn main() {
-    // Initialize logger
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .format_timestamp_millis()
-        .init();
+    // Run by the shell on every tab: nothing but the candidates may be printed, and clap
+    // would list it in the completion scripts even hidden
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(completion::COMPLETE_COMMAND) {
+        let words = args[2..].strip_prefix(&["--".to_string()]).unwrap_or(&args[2..]);
+        exit(if cli::complete(words) { 0 } else { 1 });
+    }
 
     let cli = Cli::parse();
+    error::QeError::set_debug(cli.debug);
+
+    if cli.no_color {
+        colored::control::set_override(false);
+    }
+    
+    let options = output::OutputMode::parse(&cli.output).and_then(|mode| {
+        output::init(output::OutputOptions {
+            mode,
+            template: cli.format.clone(),
+            quiet: cli.quiet,
+        })
+    });
+    let setup = options.and_then(|_| config::ConfigManager::set_cli_overrides(&cli.overrides));
+    if let Err(e) = setup {
+        report_error(&e, cli.debug);
+        exit(e.exit_code());
+    }
+    
+    // After the overrides, which may move the logs directory
+    logging::init(cli.verbose);
+    log::debug!("Command line: {:?}", std::env::args().collect::<Vec<_>>());
 
     // Execute command
     let result = match cli.command {
-        Commands::Install { name, tag } => cli::install(name, tag),
-        Commands::Start { name, image } => cli::start(name, image),
+        Commands::Install { name, flavour, tag, platform, insecure } => {
+            cli::install(name, flavour, tag, platform, insecure)
+        }
+        Commands::Start { name, image, insecure } => cli::start(name, image, insecure),
         Commands::Stop { name } => cli::stop(name),
         Commands::Exec { name, command } => cli::exec(name, command),
-        Commands::Images => cli::list_images(),
+        Commands::Images { action, available, refresh } => match action {
+            None if available => cli::list_available(refresh),
+            None => cli::list_images(),
+            Some(ImagesCommand::Inspect { reference }) => cli::inspect_image(reference),
+            Some(ImagesCommand::RemoteTags { repository }) => cli::remote_tags(repository),
+            Some(ImagesCommand::RemoteInspect { reference }) => cli::remote_inspect(reference),
+        },
         Commands::Ps => cli::list_containers(),
-        Commands::Remove { name } => cli::remove_container(name),
-        Commands::Update { image } => cli::update(image),
-        Commands::Build { dockerfile } => cli::build(dockerfile),
+        Commands::Diff { container, paths, exclude, exclude_noise } => {
+            cli::diff(container, paths, exclude, exclude_noise)
+        }
+        Commands::Df => cli::disk_usage(),
+        Commands::Prune { containers, images, dangling, older_than, keep_last, yes } => {
+            cli::prune(containers, images, dangling, older_than, keep_last, yes)
+        }
+        Commands::Remove { name, yes } => cli::remove_container(name, yes),
+        Commands::Update { image, check, insecure } => cli::update(image, check, insecure),
+        Commands::Build { dockerfile, tag, platform } => cli::build(dockerfile, tag, platform),
         Commands::Version => cli::version(),
-        Commands::Config => cli::config(),
+        Commands::Config { action } => match action {
+            None => cli::config_show(false),
+            Some(ConfigCommand::Show { origin }) => cli::config_show(origin),
+            Some(ConfigCommand::Get { key }) => cli::config_get(key),
+            Some(ConfigCommand::Set { key, value }) => cli::config_set(key, value),
+            Some(ConfigCommand::Unset { key }) => cli::config_unset(key),
+            Some(ConfigCommand::Edit) => cli::config_edit(),
+            Some(ConfigCommand::Reset { yes }) => cli::config_reset(yes),
+            Some(ConfigCommand::Validate) => cli::config_validate(),
+            Some(ConfigCommand::Path { all }) => cli::config_path(all),
+            Some(ConfigCommand::Schema { stdout }) => cli::config_schema(stdout),
+            Some(ConfigCommand::Convert { to }) => cli::config_convert(to),
+            Some(ConfigCommand::Import { path }) => cli::config_import(path),
+        },
         Commands::Restart { name } => cli::restart(name),
-        Commands::Uninstall { name } => cli::uninstall(name),
+        Commands::Uninstall { name, yes } => cli::uninstall(name, yes),
+        Commands::Profile { action } => match action {
+            ProfileCommand::List => cli::list_profiles(),
+            ProfileCommand::Show { name } => cli::show_profile(name),
+            ProfileCommand::Create {
+                name,
+                extends,
+                description,
+                image,
+                network,
+                mounts,
+                ports,
+                env,
+                capabilities,
+                memory,
+                cpus,
+                shell,
+            } => cli::create_profile(
+                name,
+                config::Profile {
+                    extends,
+                    description,
+                    image,
+                    network_mode: network,
+                    mounts,
+                    ports,
+                    env: Default::default(),
+                    capabilities,
+                    memory,
+                    cpus,
+                    shell,
+                },
+                env,
+            ),
+        },
+        Commands::Login { registry, username, password_stdin } => {
+            cli::login(registry, username, password_stdin)
+        }
+        Commands::Logout { registry } => cli::logout(registry),
+        Commands::Credentials { action } => match action {
+            CredentialsCommand::List => cli::list_credentials(),
+        },
         Commands::Activate { key } => cli::activate(key),
+        Commands::Completions { shell } => cli::completions(shell, &mut Cli::command()),
     };
 
     match result {
         Ok(_) => {
-            println!("\n{}", "✓ Opération réussie".green());
+            if output::is_human() {
+                eprintln!("\n{}", t!("common.success").green());
+            }
             exit(0);
         }
         Err(e) => {
-            eprintln!("\n{}: {}", "Erreur".red(), e);
-            exit(1);
+            eprintln!();
+            report_error(&e, cli.debug);
+            exit(e.exit_code());
         }
     }
 }
 
-fn get_help_text() -> String {
-    format!(
-        r#"
-{}
+/// Print an error, with its causes one per line and its backtrace under `--debug`
+fn report_error(e: &error::QeError, debug: bool) {
+    if !debug {
+        eprintln!("{}: {}", t!("common.error").red(), e);
+        return;
+    }
     
-{}
-
-Usage: quantum-exegol <COMMAND>
-
-Commands:
-  install    Install a new security environment image
-  start      Start a container with selected environment
-  stop       Stop a running container
-  exec       Execute command in running container
-  images     List available images
-  ps         List running containers
-  remove     Remove a container
-  update     Update images and wrapper
-  build      Build custom image
-  config     Configure Quantum Exegol
-  version    Display version information
-  help       Display this help information
-
-Options:
-  -v, --verbose  Enable verbose output
-  -h, --help     Display help information
-
-{}
-"#,
-        "╔═══════════════════════════════════════════════════════════════╗".cyan(),
-        "║      QUANTUM EXEGOL - Cybersecurity Environment Framework     ║".cyan(),
-        "╚═══════════════════════════════════════════════════════════════╝".cyan()
-    )
+    let chain = e.chain();
+    eprintln!("{}: {}", t!("common.error").red(), chain[0]);
+    for cause in &chain[1..] {
+        eprintln!("  {} {}", t!("common.cause").yellow(), cause);
+    }
+    eprintln!("  {} {}", t!("common.exit_code").yellow(), e.exit_code());
+    if let Some(backtrace) = e.backtrace() {
+        eprintln!("\n{}", backtrace);
+    }
 }
@@ -1,14 +1,84 @@
 //! Manager Module - Central management system
 
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
 use crate::container::ContainerManager;
-use crate::image::ImageManager;
+use crate::error::{QeError, ResultExt};
+use crate::docker::{self, DiskUsageInfo};
+use crate::image::{is_managed_repository, split_reference, ImageManager, UpdateStatus};
 use crate::config::ConfigManager;
+use crate::paths;
+use crate::registry::ImageReference;
+use crate::signature;
+
+/// Space used by one kind of managed resource
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsageRow {
+    pub kind: &'static str,
+    pub total: usize,
+    pub active: usize,
+    pub size: i64,
+    pub reclaimable: i64,
+}
+
+/// Selection of resources to prune
+#[derive(Debug, Clone, Default)]
+pub struct PruneOptions {
+    /// Remove stopped managed containers
+    pub containers: bool,
+    /// Remove unused tagged managed images
+    pub images: bool,
+    /// Remove dangling managed images
+    pub dangling: bool,
+    /// Only remove resources created longer ago than this
+    pub older_than: Option<chrono::Duration>,
+    /// Keep the N most recent images of each repository
+    pub keep_last: Option<usize>,
+}
+
+/// A container or image selected for removal
+#[derive(Debug, Clone)]
+pub struct PruneCandidate {
+    pub id: String,
+    /// References to remove (container name, image tags, or the image id when dangling)
+    pub refs: Vec<String>,
+    pub size: i64,
+}
+
+/// Resources that a prune would remove
+#[derive(Debug, Clone, Default)]
+pub struct PrunePlan {
+    pub containers: Vec<PruneCandidate>,
+    pub images: Vec<PruneCandidate>,
+}
+
+impl PrunePlan {
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty() && self.images.is_empty()
+    }
+
+    /// Estimated space freed by the plan
+    pub fn reclaimable(&self) -> i64 {
+        self.containers.iter().chain(&self.images).map(|c| c.size).sum()
+    }
+}
+
+/// Persisted state of the image update checks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UpdateState {
+    last_check: Option<chrono::DateTime<chrono::Utc>>,
+}
 
 pub struct ExegolManager;
 
 impl ExegolManager {
     /// Initialize the manager
-    pub fn init() -> Result<(), String> {
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn init() -> Result<(), QeError> {
         // Load or create config
         let config = ConfigManager::load();
         ConfigManager::save(&config)?;
@@ -20,48 +90,411 @@ impl ExegolManager {
     }
 
     /// Check if Docker is available
-    fn check_docker() -> Result<(), String> {
-        println!("Checking Docker availability...");
+    fn check_docker() -> Result<(), QeError> {
+        say!("{}", t!("status.checking_docker"));
         // TODO: Implement actual Docker connectivity check
-        println!("✓ Docker is available");
+        say!("{}", t!("status.docker_available"));
         Ok(())
     }
 
     /// Update wrapper (self-update)
-    pub fn update_wrapper() -> Result<(), String> {
-        println!("Checking for updates...");
+    #[allow(dead_code)] // Not reachable from the CLI yet
+    pub fn update_wrapper() -> Result<(), QeError> {
+        say!("{}", t!("update.wrapper_checking"));
         // TODO: Implement self-update mechanism
-        println!("✓ Already up to date");
+        say!("{}", t!("update.wrapper_up_to_date"));
         Ok(())
     }
 
-    /// Update images
-    pub fn update_images() -> Result<(), String> {
-        println!("Updating images...");
-        // TODO: Implement image update
+    /// Check installed images against their registry and pull the outdated ones
+    pub fn update_images(only: Option<&str>, check_only: bool, insecure: bool) -> Result<Vec<UpdateStatus>, QeError> {
+        let mut statuses = ImageManager::check_updates(only)?;
+        for status in &statuses {
+            debug!(
+                "{}: local {} remote {}",
+                status.reference,
+                status.local_digest.as_deref().unwrap_or("none"),
+                status.remote_digest.as_deref().unwrap_or("unknown")
+            );
+        }
+        
+        if !check_only {
+            for status in statuses.iter_mut().filter(|s| s.outdated) {
+                // Without the registry digest there is nothing to verify or pin
+                let Some(remote_digest) = status.remote_digest.clone() else {
+                    warn!("{}: registry gave no digest, not updating", status.reference);
+                    status.outdated = false;
+                    status.error = Some("registry gave no digest, not updated".to_string());
+                    continue;
+                };
+
+                // The verified digest is pulled, not whatever the tag points to by then
+                let verified = if insecure {
+                    None
+                } else {
+                    let reference = ImageReference::parse(&status.reference)?;
+                    Some(signature::verify(&reference, &remote_digest)?.digest)
+                };
+                
+                let (repository, tag) = split_reference(&status.reference);
+                info!("Updating {} to {}", status.reference, remote_digest);
+                say!("  {}", t!("update.pulling", image = status.reference));
+                ImageManager::pull(&repository, &tag, verified.as_deref(), status.platform.as_ref())?;
+            }
+            // A dry run leaves the automatic update due
+            Self::record_update_check()?;
+        }
+        
+        Ok(statuses)
+    }
+
+    /// Update images if `auto_update` is on and the last check is older than the configured interval
+    pub fn auto_update(insecure: bool) -> Result<(), QeError> {
+        let config = ConfigManager::load();
+        if !config.auto_update {
+            debug!("Auto update disabled");
+            return Ok(());
+        }
+        
+        // An interval too large for a duration can never elapse
+        let interval = i64::try_from(config.update_interval_hours)
+            .ok()
+            .and_then(chrono::TimeDelta::try_hours);
+        let last_check = Self::load_update_state().last_check;
+        let due = match (last_check, interval) {
+            (None, _) => true,
+            (Some(last), Some(interval)) => chrono::Utc::now() - last >= interval,
+            (Some(_), None) => false,
+        };
+        if !due {
+            debug!("Auto update not due, last check {:?}, interval {}h", last_check, config.update_interval_hours);
+            return Ok(());
+        }
+        
+        say!("{}", t!("update.auto_checking"));
+        let statuses = Self::update_images(None, false, insecure)?;
+        let updated = statuses.iter().filter(|s| s.outdated).count();
+        say!("{}", t!("update.auto_done", count = updated));
         Ok(())
     }
 
+    fn update_state_path() -> PathBuf {
+        paths::state_dir().join("update-state.json")
+    }
+
+    fn load_update_state() -> UpdateState {
+        fs::read_to_string(Self::update_state_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Remember when images were last checked, for `auto_update`
+    fn record_update_check() -> Result<(), QeError> {
+        let path = Self::update_state_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        
+        let state = UpdateState {
+            last_check: Some(chrono::Utc::now()),
+        };
+        let content = serde_json::to_string_pretty(&state)
+            .map_err(|e| format!("Failed to serialize update state: {}", e))?;
+        fs::write(&path, content).context("Failed to write update state")
+    }
+
+    /// Report space used by managed images, containers, volumes and build cache
+    pub fn disk_usage() -> Result<Vec<DiskUsageRow>, QeError> {
+        let usage = docker::block_on(docker::disk_usage())?;
+        let DiskUsageInfo { images, containers, volumes, build_cache } = usage;
+        
+        let images: Vec<_> = images.into_iter().filter(ImageManager::is_managed).collect();
+        let containers: Vec<_> = containers.into_iter().filter(ContainerManager::is_managed).collect();
+        let volumes: Vec<_> = volumes
+            .into_iter()
+            .filter(|v| v.labels.contains_key(crate::image::MANAGED_LABEL))
+            .collect();
+        
+        // Only layers not shared with another image are freed when an image is removed
+        let unique = |i: &docker::ImageInfo| i.size - i.shared_size.max(0);
+        
+        Ok(vec![
+            DiskUsageRow {
+                kind: "Images",
+                total: images.len(),
+                active: images.iter().filter(|i| i.containers > 0).count(),
+                size: images.iter().map(|i| i.size).sum(),
+                reclaimable: images.iter().filter(|i| i.containers <= 0).map(unique).sum(),
+            },
+            DiskUsageRow {
+                kind: "Containers",
+                total: containers.len(),
+                active: containers.iter().filter(|c| !ContainerManager::is_stopped(c)).count(),
+                size: containers.iter().map(|c| c.size_rw).sum(),
+                reclaimable: containers
+                    .iter()
+                    .filter(|c| ContainerManager::is_stopped(c))
+                    .map(|c| c.size_rw)
+                    .sum(),
+            },
+            DiskUsageRow {
+                kind: "Volumes",
+                total: volumes.len(),
+                active: volumes.iter().filter(|v| v.ref_count > 0).count(),
+                size: volumes.iter().map(|v| v.size.max(0)).sum(),
+                reclaimable: volumes.iter().filter(|v| v.ref_count == 0).map(|v| v.size.max(0)).sum(),
+            },
+            DiskUsageRow {
+                kind: "Build cache",
+                total: build_cache.len(),
+                active: build_cache.iter().filter(|b| b.in_use).count(),
+                size: build_cache.iter().map(|b| b.size).sum(),
+                reclaimable: build_cache
+                    .iter()
+                    .filter(|b| !b.in_use && !b.shared)
+                    .map(|b| b.size)
+                    .sum(),
+            },
+        ])
+    }
+
+    /// Select the managed containers and images a prune would remove
+    pub fn plan_prune(options: &PruneOptions) -> Result<PrunePlan, QeError> {
+        let usage = docker::block_on(docker::disk_usage())?;
+        let default_image = ConfigManager::load().default_image;
+        let now = chrono::Utc::now().timestamp();
+        let old_enough = |created: i64| {
+            options
+                .older_than
+                .is_none_or(|age| now - created >= age.num_seconds())
+        };
+        
+        let mut plan = PrunePlan::default();
+        
+        if options.containers {
+            plan.containers = usage
+                .containers
+                .iter()
+                .filter(|c| ContainerManager::is_managed(c) && ContainerManager::is_stopped(c))
+                .filter(|c| old_enough(c.created))
+                .map(|c| PruneCandidate {
+                    id: c.id.clone(),
+                    refs: vec![c
+                        .names
+                        .first()
+                        .map(|n| n.trim_start_matches('/').to_string())
+                        .unwrap_or_else(|| c.id.clone())],
+                    size: c.size_rw,
+                })
+                .collect();
+        }
+        
+        let unused: Vec<_> = usage
+            .images
+            .iter()
+            .filter(|i| ImageManager::is_managed(i) && i.containers <= 0)
+            .filter(|i| !i.repo_tags.contains(&default_image))
+            .collect();
+        
+        if options.dangling {
+            plan.images.extend(
+                unused
+                    .iter()
+                    .filter(|i| ImageManager::is_dangling(i) && old_enough(i.created))
+                    .map(|i| PruneCandidate {
+                        id: i.id.clone(),
+                        refs: vec![i.id.clone()],
+                        size: i.size - i.shared_size.max(0),
+                    }),
+            );
+        }
+        
+        if options.images {
+            let tagged: Vec<_> = usage
+                .images
+                .iter()
+                .filter(|i| ImageManager::is_managed(i) && !ImageManager::is_dangling(i))
+                .collect();
+            let kept = keep_last(&tagged, options.keep_last.unwrap_or(0));
+            
+            for image in tagged {
+                let selected = plan.images.iter().any(|c| c.id == image.id);
+                if selected
+                    || kept.contains(&image.id)
+                    || !unused.iter().any(|u| u.id == image.id)
+                    || !old_enough(image.created)
+                {
+                    continue;
+                }
+                plan.images.push(PruneCandidate {
+                    id: image.id.clone(),
+                    refs: managed_refs(image),
+                    size: image.size - image.shared_size.max(0),
+                });
+            }
+        }
+        
+        Ok(plan)
+    }
+
+    /// Remove everything selected by a prune plan, returning the space reclaimed
+    pub fn prune(plan: &PrunePlan) -> Result<i64, QeError> {
+        let mut reclaimed = 0;
+        info!("Pruning {} container(s) and {} image(s)", plan.containers.len(), plan.images.len());
+        
+        for container in &plan.containers {
+            docker::block_on(docker::remove_container(&container.id, false))?;
+            debug!("Removed container {} ({} bytes)", container.id, container.size);
+            say!("  {}", t!("prune.removed_container", name = container.refs.join(", ")));
+            reclaimed += container.size;
+        }
+        
+        for image in &plan.images {
+            let mut deleted = false;
+            for reference in &image.refs {
+                deleted |= docker::block_on(docker::remove_image(reference, false))?;
+            }
+            // Tags outside the plan keep the image, and its space, alive
+            if deleted {
+                debug!("Removed image {} ({} bytes)", image.id, image.size);
+                say!("  {}", t!("prune.removed_image", name = image.refs.join(", ")));
+                reclaimed += image.size;
+            } else {
+                debug!("Untagged image {}, other tags still use it", image.id);
+                say!("  {}", t!("prune.untagged_image", name = image.refs.join(", ")));
+            }
+        }
+        
+        info!("Pruned {} bytes", reclaimed);
+        Ok(reclaimed)
+    }
+
     /// Display status
+    #[allow(dead_code)] // Not reachable from the CLI yet
     pub fn status() {
-        println!("╔═══════════════════════════════════════════════════════════════╗");
-        println!("║                    QUANTUM EXEGOL STATUS                    ║");
-        println!("╚═══════════════════════════════════════════════════════════════╝");
+        say!("╔═══════════════════════════════════════════════════════════════╗");
+        say!("║                    QUANTUM EXEGOL STATUS                    ║");
+        say!("╚═══════════════════════════════════════════════════════════════╝");
         
         // Show config
         let config = ConfigManager::load();
-        println!("\nConfiguration:");
-        println!("  Docker Socket: {}", config.docker_socket);
-        println!("  Default Image: {}", config.default_image);
-        println!("  Data Dir: {}", config.data_dir);
-        println!("  Workspace: {}", config.workspace);
+        say!("\n{}", t!("status.configuration"));
+        say!("  {}", t!("status.docker_socket", value = config.docker_socket));
+        say!("  {}", t!("status.default_image", value = config.default_image));
+        say!("  {}", t!("status.data_dir", value = paths::data_dir().display()));
+        say!("  {}", t!("status.workspace", value = paths::workspace_dir().display()));
         
         // Show containers
-        let containers = ContainerManager::list_running();
-        println!("\nRunning Containers: {}", containers.len());
+        let running = match ContainerManager::list_running() {
+            Ok(containers) => containers.len().to_string(),
+            Err(e) => t!("status.unknown", error = e),
+        };
+        say!("\n{}", t!("status.running", count = running));
         
         // Show images
-        let images = ImageManager::list();
-        println!("Available Images: {}", images.len());
+        let images = match ImageManager::list() {
+            Ok(images) => images.len().to_string(),
+            Err(e) => t!("status.unknown", error = e),
+        };
+        say!("{}", t!("status.images", count = images));
+    }
+}
+
+/// Ids of the images `--keep-last` keeps: the `keep` newest of each repository
+///
+/// An image tagged several times in a repository counts once there, and an image tagged
+/// in several repositories is kept if any of them keeps it.
+fn keep_last(images: &[&docker::ImageInfo], keep: usize) -> HashSet<String> {
+    let mut by_repository: HashMap<String, Vec<&docker::ImageInfo>> = HashMap::new();
+    for image in images {
+        for repository in managed_refs(image).iter().map(|r| split_reference(r).0) {
+            let bucket = by_repository.entry(repository).or_default();
+            if !bucket.iter().any(|i| i.id == image.id) {
+                bucket.push(image);
+            }
+        }
+    }
+    
+    by_repository
+        .into_values()
+        .flat_map(|mut bucket| {
+            bucket.sort_by_key(|i| std::cmp::Reverse(i.created));
+            bucket.into_iter().take(keep).map(|i| i.id.clone())
+        })
+        .collect()
+}
+
+/// Tags of an image in the wrapper's namespace, or all of them for a labelled image
+fn managed_refs(image: &docker::ImageInfo) -> Vec<String> {
+    let managed: Vec<String> = image
+        .repo_tags
+        .iter()
+        .filter(|r| is_managed_repository(&split_reference(r).0))
+        .cloned()
+        .collect();
+    
+    if managed.is_empty() {
+        image.repo_tags.clone()
+    } else {
+        managed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(id: &str, created: i64, tags: &[&str]) -> docker::ImageInfo {
+        docker::ImageInfo {
+            id: id.to_string(),
+            repo_tags: tags.iter().map(|t| t.to_string()).collect(),
+            repo_digests: Vec::new(),
+            size: 0,
+            shared_size: 0,
+            created,
+            labels: HashMap::new(),
+            containers: 0,
+        }
+    }
+
+    fn kept(images: &[docker::ImageInfo], keep: usize) -> Vec<String> {
+        let images: Vec<_> = images.iter().collect();
+        let mut kept: Vec<_> = keep_last(&images, keep).into_iter().collect();
+        kept.sort();
+        kept
+    }
+
+    #[test]
+    fn keep_last_keeps_the_newest_of_each_repository() {
+        let images = [
+            image("a1", 100, &["quantum/security:old"]),
+            image("a2", 200, &["quantum/security:mid"]),
+            image("a3", 300, &["quantum/security:new"]),
+            image("b1", 100, &["quantum/web:old"]),
+            image("b2", 200, &["quantum/web:new"]),
+        ];
+        assert_eq!(kept(&images, 1), ["a3", "b2"]);
+        assert_eq!(kept(&images, 2), ["a2", "a3", "b1", "b2"]);
+        assert!(kept(&images, 0).is_empty());
+    }
+
+    #[test]
+    fn keep_last_counts_an_image_tagged_twice_once() {
+        let images = [
+            image("a1", 100, &["quantum/security:old"]),
+            image("a2", 300, &["quantum/security:latest", "quantum/security:full"]),
+        ];
+        assert_eq!(kept(&images, 2), ["a1", "a2"]);
+    }
+
+    #[test]
+    fn keep_last_keeps_an_image_any_repository_keeps() {
+        let images = [
+            image("shared", 100, &["quantum/security:old", "quantum/web:only"]),
+            image("a2", 200, &["quantum/security:new"]),
+        ];
+        assert_eq!(kept(&images, 1), ["a2", "shared"]);
     }
 }
@@ -0,0 +1,312 @@
+//! Output Module - Tables for people, JSON, YAML, IDs or templates for scripts
+//! Messages meant for people go through `say!` so they never mix with structured output
+
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use crate::error::QeError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// One identifier per line, like `docker ps -q`
+    Ids,
+}
+
+impl OutputMode {
+    pub fn parse(name: &str) -> Result<Self, QeError> {
+        match name.to_lowercase().as_str() {
+            "table" => Ok(OutputMode::Table),
+            "json" => Ok(OutputMode::Json),
+            "yaml" | "yml" => Ok(OutputMode::Yaml),
+            "ids" => Ok(OutputMode::Ids),
+            other => Err(QeError::InvalidUsage(format!(
+                "Unknown output mode: {} (expected table, json, yaml or ids)",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    pub mode: OutputMode,
+    /// Template applied to each record, e.g. `{{.name}} {{.status}}`
+    pub template: Option<String>,
+    /// Print nothing but identifiers and requested values
+    pub quiet: bool,
+}
+
+static OPTIONS: OnceLock<OutputOptions> = OnceLock::new();
+
+/// Set the output options from the command line, once at startup
+pub fn init(options: OutputOptions) -> Result<(), QeError> {
+    if let Some(template) = &options.template {
+        check_template(template).map_err(|e| QeError::InvalidUsage(format!("Invalid --format: {}", e)))?;
+    }
+    OPTIONS
+        .set(options)
+        .map_err(|_| "Output options already set".into())
+}
+
+pub fn options() -> &'static OutputOptions {
+    OPTIONS.get_or_init(OutputOptions::default)
+}
+
+/// Whether stdout is read by a person rather than a script
+pub fn is_human() -> bool {
+    let options = options();
+    options.mode == OutputMode::Table && options.template.is_none() && !options.quiet
+}
+
+/// Print a message meant for people: on stdout for tables, on stderr next to structured output
+pub fn say(message: String) {
+    let options = options();
+    if options.quiet {
+        return;
+    }
+    if is_human() {
+        println!("{}", message);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+/// `println!` for messages meant for people, see [`say`]
+macro_rules! say {
+    () => {
+        $crate::output::say(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::output::say(format!($($arg)*))
+    };
+}
+
+/// Print records in the selected mode, `table` printing the view for people
+pub fn print_list<T: Serialize>(
+    items: &[T],
+    id: impl Fn(&T) -> String,
+    table: impl FnOnce(&[T]),
+) -> Result<(), QeError> {
+    write_list(&mut std::io::stdout(), options(), items, id, table)
+}
+
+/// Write records for scripts to `out`, leaving the table view to `table`
+fn write_list<T: Serialize>(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    items: &[T],
+    id: impl Fn(&T) -> String,
+    table: impl FnOnce(&[T]),
+) -> Result<(), QeError> {
+    if let Some(template) = &options.template {
+        for item in items {
+            writeln!(out, "{}", render(template, &to_value(item)?))?;
+        }
+        return Ok(());
+    }
+
+    match options.mode {
+        OutputMode::Ids => {
+            for item in items {
+                writeln!(out, "{}", id(item))?;
+            }
+        }
+        OutputMode::Table if options.quiet => {
+            for item in items {
+                writeln!(out, "{}", id(item))?;
+            }
+        }
+        OutputMode::Table => table(items),
+        OutputMode::Json => writeln!(out, "{}", to_json(&items)?)?,
+        OutputMode::Yaml => write!(out, "{}", to_yaml(&items)?)?,
+    }
+    Ok(())
+}
+
+/// Print a single record in the selected mode
+pub fn print_item<T: Serialize>(item: &T, id: impl Fn(&T) -> String, table: impl FnOnce(&T)) -> Result<(), QeError> {
+    let options = options();
+
+    if let Some(template) = &options.template {
+        println!("{}", render(template, &to_value(item)?));
+        return Ok(());
+    }
+
+    match options.mode {
+        OutputMode::Ids => println!("{}", id(item)),
+        OutputMode::Table if options.quiet => println!("{}", id(item)),
+        OutputMode::Table => table(item),
+        OutputMode::Json => println!("{}", to_json(item)?),
+        OutputMode::Yaml => print!("{}", to_yaml(item)?),
+    }
+    Ok(())
+}
+
+fn to_value<T: Serialize>(item: &T) -> Result<Value, String> {
+    serde_json::to_value(item).map_err(|e| format!("Failed to serialize output: {}", e))
+}
+
+fn to_json<T: Serialize + ?Sized>(item: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(item).map_err(|e| format!("Failed to serialize output: {}", e))
+}
+
+fn to_yaml<T: Serialize + ?Sized>(item: &T) -> Result<String, String> {
+    serde_yaml::to_string(item).map_err(|e| format!("Failed to serialize output: {}", e))
+}
+
+fn check_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| format!("Unclosed {{{{ in format '{}'", template))?;
+        let action = rest[start + 2..start + end].trim();
+        let field = action.strip_prefix("json ").unwrap_or(action).trim();
+        if !field.starts_with('.') {
+            return Err(format!("Invalid field '{}' in format, expected e.g. {{{{.name}}}}", action));
+        }
+        rest = &rest[start + end + 2..];
+    }
+    Ok(())
+}
+
+/// Fill a Go-like template: `{{.field}}`, `{{.nested.field}}`, `{{json .field}}`, `\t` and `\n`
+pub fn render(template: &str, value: &Value) -> String {
+    let template = template.replace("\\t", "\t").replace("\\n", "\n");
+    let mut output = String::new();
+    let mut rest = template.as_str();
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+
+        let action = rest[start + 2..start + end].trim();
+        let (as_json, field) = match action.strip_prefix("json ") {
+            Some(field) => (true, field.trim()),
+            None => (false, action),
+        };
+        let found = field
+            .trim_start_matches('.')
+            .split('.')
+            .filter(|key| !key.is_empty())
+            .try_fold(value, |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => value.get(key),
+            });
+
+        match found {
+            // Same placeholder as Go templates for missing fields
+            None => output.push_str("<no value>"),
+            Some(Value::String(s)) if !as_json => output.push_str(s),
+            Some(Value::Null) if !as_json => {}
+            Some(other) => output.push_str(&other.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Record {
+        name: &'static str,
+        status: &'static str,
+    }
+
+    const RECORDS: &[Record] = &[
+        Record { name: "web", status: "running" },
+        Record { name: "db", status: "exited" },
+    ];
+
+    /// Output of `write_list` and whether the table view was asked for
+    fn list(options: &OutputOptions, mode: OutputMode) -> (String, bool) {
+        let options = OutputOptions { mode, ..options.clone() };
+        let mut out = Vec::new();
+        let mut table = false;
+        write_list(&mut out, &options, RECORDS, |r| r.name.to_string(), |_| table = true).unwrap();
+        (String::from_utf8(out).unwrap(), table)
+    }
+
+    #[test]
+    fn render_looks_up_fields() {
+        let value = json!({"name": "web", "image": {"tag": "full"}, "ports": [80, 443], "size": 12, "note": null});
+        assert_eq!(render("{{.name}}:{{ .image.tag }}", &value), "web:full");
+        assert_eq!(render("{{.ports.1}} {{.size}}", &value), "443 12");
+        assert_eq!(render("[{{.note}}]", &value), "[]");
+        assert_eq!(render("{{json .name}} {{json .ports}}", &value), "\"web\" [80,443]");
+        assert_eq!(render("{{json .note}}", &value), "null");
+    }
+
+    #[test]
+    fn render_marks_unknown_fields() {
+        let value = json!({"name": "web", "ports": [80]});
+        assert_eq!(render("{{.missing}}", &value), "<no value>");
+        assert_eq!(render("{{.name.inner}} {{.ports.5}}", &value), "<no value> <no value>");
+    }
+
+    #[test]
+    fn render_handles_escapes() {
+        let value = json!({"name": "web", "status": "running"});
+        assert_eq!(render("{{.name}}\\t{{.status}}\\n", &value), "web\trunning\n");
+        // Text around the actions and an unclosed action are kept as is
+        assert_eq!(render("name={{.name}} {{.status", &value), "name=web {{.status");
+    }
+
+    #[test]
+    fn check_template_accepts_fields() {
+        assert!(check_template("{{.name}}\\t{{json .labels}}").is_ok());
+        assert!(check_template("no actions").is_ok());
+    }
+
+    #[test]
+    fn check_template_rejects_bad_actions() {
+        assert!(check_template("{{.name").unwrap_err().contains("Unclosed"));
+        assert!(check_template("{{name}}").unwrap_err().contains("Invalid field 'name'"));
+        assert!(check_template("{{json name}}").is_err());
+    }
+
+    #[test]
+    fn print_list_writes_json() {
+        let (out, table) = list(&OutputOptions::default(), OutputMode::Json);
+        assert!(!table);
+        let parsed: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed, json!([{"name": "web", "status": "running"}, {"name": "db", "status": "exited"}]));
+    }
+
+    #[test]
+    fn print_list_leaves_the_table_to_the_caller() {
+        let (out, table) = list(&OutputOptions::default(), OutputMode::Table);
+        assert!(table);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn print_list_writes_ids_when_quiet() {
+        let quiet = OutputOptions { quiet: true, ..OutputOptions::default() };
+        assert_eq!(list(&quiet, OutputMode::Table), ("web\ndb\n".to_string(), false));
+        assert_eq!(list(&OutputOptions::default(), OutputMode::Ids), ("web\ndb\n".to_string(), false));
+    }
+
+    #[test]
+    fn print_list_applies_the_template_over_the_mode() {
+        let options = OutputOptions {
+            template: Some("{{.name}}={{.status}}".to_string()),
+            ..OutputOptions::default()
+        };
+        assert_eq!(list(&options, OutputMode::Json), ("web=running\ndb=exited\n".to_string(), false));
+    }
+}
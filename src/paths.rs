@@ -0,0 +1,115 @@
+//! Paths Module - Where Quantum Exegol keeps its files
+//! `QE_HOME` puts everything under one directory (e.g. a USB drive), otherwise the XDG
+//! locations are used; `Config` can still move each directory except the config one
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::ConfigManager;
+use crate::utils::expand_vars;
+
+/// Environment variable holding the root of a portable install
+pub const HOME_ENV: &str = "QE_HOME";
+
+/// Directory name used below the XDG base directories
+const APP_DIR: &str = "quantum-exegol";
+
+/// Root of a portable install, if `QE_HOME` is set
+pub fn portable_home() -> Option<PathBuf> {
+    let home = std::env::var(HOME_ENV).ok().filter(|home| !home.is_empty())?;
+    Some(PathBuf::from(expand_vars(&home).unwrap_or(home)))
+}
+
+/// Configured directory with `~` and `${VAR}` expanded
+///
+/// A value that cannot be expanded is used as written, with one warning naming its key.
+fn configured(key: &str, value: &str) -> PathBuf {
+    static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+    match ConfigManager::expand(key, value) {
+        Ok(expanded) => PathBuf::from(expanded),
+        Err(e) => {
+            let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+            if warned.insert(key.to_string()) {
+                eprintln!("Warning: {}", e);
+            }
+            PathBuf::from(value)
+        }
+    }
+}
+
+/// Directory below `QE_HOME`, or below an XDG base directory
+fn resolve(portable: &str, base: Option<PathBuf>) -> PathBuf {
+    match portable_home() {
+        Some(home) => home.join(portable),
+        None => base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR),
+    }
+}
+
+/// Directory of the user config file (`$XDG_CONFIG_HOME/quantum-exegol`)
+pub fn config_dir() -> PathBuf {
+    resolve("config", dirs::config_dir())
+}
+
+/// Data directory used when `data_dir` is not configured (`$XDG_DATA_HOME/quantum-exegol`)
+pub fn default_data_dir() -> PathBuf {
+    resolve("data", dirs::data_local_dir())
+}
+
+/// Workspace used when `workspace` is not configured
+pub fn default_workspace() -> PathBuf {
+    match portable_home() {
+        Some(home) => home.join("workspace"),
+        None => dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("quantum-workspace"),
+    }
+}
+
+/// Credentials, signatures and other files that must be kept
+pub fn data_dir() -> PathBuf {
+    configured("data_dir", &ConfigManager::load().data_dir)
+}
+
+/// Shared workspace mounted in the containers
+pub fn workspace_dir() -> PathBuf {
+    configured("workspace", &ConfigManager::load().workspace)
+}
+
+/// Files that can be downloaded again, e.g. the catalog (`$XDG_CACHE_HOME/quantum-exegol`)
+pub fn cache_dir() -> PathBuf {
+    ConfigManager::load()
+        .cache_dir
+        .map(|dir| configured("cache_dir", &dir))
+        .unwrap_or_else(|| resolve("cache", dirs::cache_dir()))
+}
+
+/// Files worth keeping between runs but not backing up (`$XDG_STATE_HOME/quantum-exegol`)
+pub fn state_dir() -> PathBuf {
+    // Only Linux has a state directory, elsewhere it lives with the data
+    ConfigManager::load()
+        .state_dir
+        .map(|dir| configured("state_dir", &dir))
+        .unwrap_or_else(|| resolve("state", dirs::state_dir().or_else(dirs::data_local_dir)))
+}
+
+/// Log files, in the state directory unless configured
+pub fn logs_dir() -> PathBuf {
+    ConfigManager::load()
+        .logs_dir
+        .map(|dir| configured("logs_dir", &dir))
+        .unwrap_or_else(|| state_dir().join("logs"))
+}
+
+/// Every directory with its name, for display
+pub fn all() -> Vec<(&'static str, PathBuf)> {
+    vec![
+        ("config", config_dir()),
+        ("data", data_dir()),
+        ("cache", cache_dir()),
+        ("state", state_dir()),
+        ("logs", logs_dir()),
+        ("workspace", workspace_dir()),
+    ]
+}
@@ -0,0 +1,339 @@
+//! Profile Module - Named container settings for each kind of engagement
+//! A profile may extend another one, inheriting every value it leaves unset
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::config::{Config, ConfigManager, Profile};
+use crate::error::QeError;
+use crate::utils::{expand_vars, validate_container_name};
+
+/// Settings of a profile once its inheritance chain is applied
+#[derive(Debug, Clone, Serialize)]
+pub struct ContainerProfile {
+    pub name: String,
+    /// Profiles applied, from the base to this one
+    pub chain: Vec<String>,
+    pub description: Option<String>,
+    pub image: String,
+    pub network_mode: String,
+    pub mounts: Vec<String>,
+    pub ports: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub capabilities: Vec<String>,
+    pub memory: Option<String>,
+    pub cpus: Option<f64>,
+    pub shell: String,
+}
+
+pub struct ProfileManager;
+
+impl ProfileManager {
+    /// Profiles defined in the configuration
+    pub fn list() -> BTreeMap<String, Profile> {
+        ConfigManager::load().profiles
+    }
+
+    /// Resolve a profile and the ones it extends over the top-level config
+    ///
+    /// `~` and `${VAR}` are expanded here, in the profiles of the chain only, so an unset
+    /// variable fails the profile using it and not the whole config.
+    pub fn resolve(name: &str) -> Result<ContainerProfile, QeError> {
+        let mut config = ConfigManager::load();
+        for current in chain_of(&config, name)? {
+            if let Some(profile) = config.profiles.get_mut(&current) {
+                expand(&current, profile)?;
+            }
+        }
+        resolve_in(&config, name)
+    }
+
+    /// Add a profile to the user config file
+    pub fn create(name: &str, profile: &Profile) -> Result<(), QeError> {
+        validate_container_name(name)
+            .map_err(|e| QeError::InvalidUsage(e.replace("Container name", "Profile name")))?;
+
+        let mut config = ConfigManager::load();
+        if config.profiles.contains_key(name) {
+            return Err(QeError::Conflict(format!("Profile {} already exists", name)));
+        }
+        validate(profile).map_err(QeError::InvalidUsage)?;
+
+        // Resolve against the new set so a bad `extends` is refused before saving
+        config.profiles.insert(name.to_string(), profile.clone());
+        resolve_in(&config, name)?;
+
+        let value = serde_json::to_value(profile).map_err(|e| format!("Failed to serialize profile: {}", e))?;
+        let mut profiles = serde_json::Map::new();
+        profiles.insert(name.to_string(), value);
+        let mut layer = serde_json::Map::new();
+        layer.insert("profiles".to_string(), serde_json::Value::Object(profiles));
+        ConfigManager::merge_user_layer(serde_json::Value::Object(layer))
+    }
+}
+
+/// Names of the profiles a profile inherits from, from the base to the profile itself
+fn chain_of(config: &Config, name: &str) -> Result<Vec<String>, QeError> {
+    // Walk up the `extends` links, refusing loops
+    let mut chain: Vec<&str> = Vec::new();
+    let mut next = Some(name);
+    while let Some(current) = next {
+        if chain.contains(&current) {
+            chain.push(current);
+            return Err(QeError::ConfigInvalid(format!("Profile inheritance loop: {}", chain.join(" → "))));
+        }
+        let profile = config.profiles.get(current).ok_or_else(|| match chain.last() {
+            Some(child) => QeError::ConfigInvalid(format!("Profile {} extends unknown profile {}", child, current)),
+            None => QeError::NotFound(format!("Unknown profile: {}", name)),
+        })?;
+        chain.push(current);
+        next = profile.extends.as_deref();
+    }
+    Ok(chain.iter().rev().map(|n| n.to_string()).collect())
+}
+
+fn resolve_in(config: &Config, name: &str) -> Result<ContainerProfile, QeError> {
+    let chain = chain_of(config, name)?;
+
+    let mut resolved = ContainerProfile {
+        name: name.to_string(),
+        chain: chain.clone(),
+        description: None,
+        image: config.default_image.clone(),
+        network_mode: config.network_mode.clone(),
+        mounts: Vec::new(),
+        ports: Vec::new(),
+        env: BTreeMap::new(),
+        capabilities: Vec::new(),
+        memory: None,
+        cpus: None,
+        shell: config.default_shell.clone(),
+    };
+
+    for profile in chain.iter().map(|n| &config.profiles[n]) {
+        if let Some(description) = &profile.description {
+            resolved.description = Some(description.clone());
+        }
+        if let Some(image) = &profile.image {
+            resolved.image = image.clone();
+        }
+        if let Some(network_mode) = &profile.network_mode {
+            resolved.network_mode = network_mode.clone();
+        }
+        extend_unique(&mut resolved.mounts, &profile.mounts);
+        extend_unique(&mut resolved.ports, &profile.ports);
+        extend_unique(&mut resolved.capabilities, &profile.capabilities);
+        resolved.env.extend(profile.env.clone());
+        if profile.memory.is_some() {
+            resolved.memory = profile.memory.clone();
+        }
+        if profile.cpus.is_some() {
+            resolved.cpus = profile.cpus;
+        }
+        if let Some(shell) = &profile.shell {
+            resolved.shell = shell.clone();
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Expand `~` and `${VAR}` in the values of a profile, naming the key of a value that fails
+fn expand(name: &str, profile: &mut Profile) -> Result<(), QeError> {
+    let key = |field: &str| format!("profiles.{}.{}", name, field);
+
+    for (field, value) in [
+        ("image", &mut profile.image),
+        ("network_mode", &mut profile.network_mode),
+        ("memory", &mut profile.memory),
+        ("shell", &mut profile.shell),
+    ] {
+        if let Some(value) = value {
+            *value = ConfigManager::expand(&key(field), value)?;
+        }
+    }
+    for (field, values) in [("mounts", &mut profile.mounts), ("ports", &mut profile.ports)] {
+        for value in values.iter_mut() {
+            *value = ConfigManager::expand(&key(field), value)?;
+        }
+    }
+    for (variable, value) in profile.env.iter_mut() {
+        *value = ConfigManager::expand(&key(&format!("env.{}", variable)), value)?;
+    }
+
+    // Values are only checked once expanded, the stored ones may still hold references
+    validate(profile).map_err(|e| QeError::ConfigInvalid(format!("Profile {}: {}", name, e)))
+}
+
+fn extend_unique(target: &mut Vec<String>, items: &[String]) {
+    for item in items {
+        if !target.contains(item) {
+            target.push(item.clone());
+        }
+    }
+}
+
+/// Check the syntax of the values Docker will receive
+///
+/// A value referencing an unset variable is skipped, it is checked again once expanded.
+fn validate(profile: &Profile) -> Result<(), String> {
+    let expanded = |values: &[String]| -> Vec<String> { values.iter().filter_map(|v| expand_vars(v).ok()).collect() };
+
+    for mount in &expanded(&profile.mounts) {
+        let parts: Vec<&str> = mount.split(':').collect();
+        let valid = match parts.as_slice() {
+            [host, container] => !host.is_empty() && container.starts_with('/'),
+            [host, container, mode] => {
+                !host.is_empty() && container.starts_with('/') && matches!(*mode, "ro" | "rw")
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(format!("Invalid mount '{}', expected host:container[:ro]", mount));
+        }
+    }
+
+    for port in &expanded(&profile.ports) {
+        let (mapping, proto) = port.split_once('/').unwrap_or((port, "tcp"));
+        let numbers: Vec<&str> = mapping.rsplitn(3, ':').take(2).collect();
+        let valid = matches!(proto, "tcp" | "udp" | "sctp")
+            && numbers.len() == 2
+            && numbers.iter().all(|n| n.parse::<u16>().is_ok());
+        if !valid {
+            return Err(format!("Invalid port '{}', expected [ip:]host:container[/proto]", port));
+        }
+    }
+
+    if let Some(memory) = profile.memory.as_deref().and_then(|m| expand_vars(m).ok()) {
+        parse_memory(&memory)?;
+    }
+    if let Some(cpus) = profile.cpus {
+        if !cpus.is_finite() || cpus <= 0.0 {
+            return Err(format!("Invalid CPU limit {}, expected a positive number", cpus));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a memory limit such as `512m` or `4g` into bytes
+pub fn parse_memory(value: &str) -> Result<i64, String> {
+    let value = value.trim().to_lowercase();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("Invalid memory limit '{}', expected e.g. 512m or 4g", value))?;
+    let multiplier = match unit.trim_end_matches('b') {
+        "" => 1,
+        "k" => 1024,
+        "m" => 1024 * 1024,
+        "g" => 1024 * 1024 * 1024,
+        _ => return Err(format!("Invalid memory unit in '{}', expected k, m or g", value)),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Invalid memory limit '{}', too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(extends: Option<&str>) -> Profile {
+        Profile {
+            extends: extends.map(str::to_string),
+            ..Profile::default()
+        }
+    }
+
+    fn config_with(profiles: Vec<(&str, Profile)>) -> Config {
+        Config {
+            profiles: profiles.into_iter().map(|(name, p)| (name.to_string(), p)).collect(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn parse_memory_reads_units() {
+        assert_eq!(parse_memory("512").unwrap(), 512);
+        assert_eq!(parse_memory("2k").unwrap(), 2048);
+        assert_eq!(parse_memory("512m").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_memory(" 4G ").unwrap(), 4 * 1024 * 1024 * 1024);
+        assert_eq!(parse_memory("1gb").unwrap(), 1024 * 1024 * 1024);
+    }
+
+    #[test]
+    fn parse_memory_rejects_bad_input() {
+        assert!(parse_memory("").is_err());
+        assert!(parse_memory("g").is_err());
+        assert!(parse_memory("-1g").is_err());
+        assert!(parse_memory("4t").is_err());
+        assert!(parse_memory("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn parse_memory_rejects_overflow_instead_of_panicking() {
+        let error = parse_memory("9999999999999g").unwrap_err();
+        assert!(error.starts_with("Invalid memory limit"), "{}", error);
+        assert_eq!(parse_memory(&format!("{}", i64::MAX)).unwrap(), i64::MAX);
+    }
+
+    #[test]
+    fn validate_rejects_cpu_limits_that_are_not_positive_numbers() {
+        for cpus in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let profile = Profile { cpus: Some(cpus), ..profile(None) };
+            assert!(validate(&profile).is_err(), "{}", cpus);
+        }
+        assert!(validate(&Profile { cpus: Some(1.5), ..profile(None) }).is_ok());
+    }
+
+    #[test]
+    fn resolve_in_applies_the_chain_from_the_base() {
+        let base = Profile {
+            image: Some("quantum/security:full".to_string()),
+            mounts: vec!["/data:/data".to_string()],
+            env: BTreeMap::from([("A".to_string(), "base".to_string()), ("B".to_string(), "base".to_string())]),
+            memory: Some("4g".to_string()),
+            ..profile(None)
+        };
+        let child = Profile {
+            mounts: vec!["/data:/data".to_string(), "/tools:/tools:ro".to_string()],
+            env: BTreeMap::from([("B".to_string(), "child".to_string())]),
+            shell: Some("/bin/zsh".to_string()),
+            ..profile(Some("base"))
+        };
+        let config = config_with(vec![("base", base), ("child", child)]);
+
+        let resolved = resolve_in(&config, "child").unwrap();
+        assert_eq!(resolved.chain, ["base", "child"]);
+        assert_eq!(resolved.image, "quantum/security:full");
+        assert_eq!(resolved.mounts, ["/data:/data", "/tools:/tools:ro"]);
+        assert_eq!(resolved.env["A"], "base");
+        assert_eq!(resolved.env["B"], "child");
+        assert_eq!(resolved.memory.as_deref(), Some("4g"));
+        assert_eq!(resolved.shell, "/bin/zsh");
+        // Unset everywhere in the chain, taken from the top-level config
+        assert_eq!(resolved.network_mode, config.network_mode);
+    }
+
+    #[test]
+    fn resolve_in_refuses_inheritance_loops() {
+        let config = config_with(vec![("a", profile(Some("b"))), ("b", profile(Some("c"))), ("c", profile(Some("a")))]);
+        match resolve_in(&config, "a") {
+            Err(QeError::ConfigInvalid(message)) => assert!(message.contains("a → b → c → a"), "{}", message),
+            other => panic!("expected a loop error, got {:?}", other.map(|p| p.chain)),
+        }
+
+        let config = config_with(vec![("self", profile(Some("self")))]);
+        assert!(matches!(resolve_in(&config, "self"), Err(QeError::ConfigInvalid(_))));
+    }
+
+    #[test]
+    fn resolve_in_reports_unknown_profiles() {
+        let config = config_with(vec![("child", profile(Some("missing")))]);
+        assert!(matches!(resolve_in(&config, "child"), Err(QeError::ConfigInvalid(_))));
+        assert!(matches!(resolve_in(&config, "nope"), Err(QeError::NotFound(_))));
+    }
+}
@@ -0,0 +1,207 @@
+//! Signature Module - Ed25519 verification of image manifest digests
+//! Signatures are detached JSON files, read from the data directory or a registry sidecar tag
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{ConfigManager, TrustedKey};
+use crate::docker;
+use crate::error::QeError;
+use crate::image::{same_repository, split_reference};
+use crate::paths;
+use crate::registry::{ImageReference, RegistryClient, RemoteManifest};
+
+/// Detached signature of a manifest digest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageSignature {
+    /// Signed manifest digest, e.g. `sha256:...`
+    pub digest: String,
+    /// Ed25519 signature over the digest string, base64-encoded
+    pub signature: String,
+}
+
+/// Outcome of a successful verification
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub digest: String,
+    /// Name of the trusted key that produced the signature
+    pub key: String,
+    /// Where the signature was found
+    pub source: String,
+}
+
+/// Tag under which a registry stores the signature of a manifest (`sha256-<hex>.sig`)
+pub fn sidecar_tag(digest: &str) -> String {
+    format!("{}.sig", digest.replace(':', "-"))
+}
+
+/// Local signature file for a manifest digest
+pub fn local_signature_path(digest: &str) -> PathBuf {
+    paths::data_dir()
+        .join("signatures")
+        .join(sidecar_tag(digest))
+}
+
+/// Verify a signature over a digest against the trusted keys, returning the matching key name
+pub fn verify_digest(digest: &str, signature: &ImageSignature, keys: &[TrustedKey]) -> Result<String, String> {
+    if signature.digest != digest {
+        return Err(format!(
+            "Signature is for {} but the image manifest is {}",
+            signature.digest, digest
+        ));
+    }
+
+    let signature = BASE64
+        .decode(signature.signature.trim())
+        .map_err(|e| format!("Invalid signature encoding: {}", e))?;
+
+    for key in keys {
+        let public_key = BASE64
+            .decode(key.public_key.trim())
+            .map_err(|e| format!("Invalid public key '{}': {}", key.name, e))?;
+        if UnparsedPublicKey::new(&ED25519, &public_key)
+            .verify(digest.as_bytes(), &signature)
+            .is_ok()
+        {
+            return Ok(key.name.clone());
+        }
+    }
+
+    Err(format!("Signature for {} does not match any trusted key", digest))
+}
+
+/// Verify a manifest digest of an image, looking for its signature locally then on the registry
+pub fn verify(reference: &ImageReference, digest: &str) -> Result<Verification, QeError> {
+    let keys = ConfigManager::load().trusted_keys;
+    if keys.is_empty() {
+        return Err("No trusted signing keys configured (add trusted_keys to the config or pass --insecure)".into());
+    }
+
+    let (signature, source) = find_signature(reference, digest)?
+        .ok_or_else(|| format!("Image {} is not signed (pass --insecure to use it anyway)", reference))?;
+    let key = verify_digest(digest, &signature, &keys)?;
+
+    Ok(Verification {
+        digest: digest.to_string(),
+        key,
+        source,
+    })
+}
+
+/// Verify an installed image using its local repository digest
+pub fn verify_local(reference: &str) -> Result<Verification, QeError> {
+    let parsed = ImageReference::parse(reference)?;
+    let details = docker::block_on(docker::inspect_image(reference))?;
+
+    let (repository, _) = split_reference(reference);
+    let digest = details
+        .repo_digests
+        .iter()
+        .filter_map(|d| d.split_once('@'))
+        .find(|(repo, _)| same_repository(repo, &repository))
+        .map(|(_, digest)| digest.to_string())
+        .ok_or_else(|| format!("Image {} has no registry digest and cannot be verified", reference))?;
+
+    verify(&parsed, &digest)
+}
+
+/// Find a detached signature in the data directory or as a registry sidecar tag
+fn find_signature(reference: &ImageReference, digest: &str) -> Result<Option<(ImageSignature, String)>, QeError> {
+    let path = local_signature_path(digest);
+    if path.exists() {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read signature {:?}: {}", path, e))?;
+        let signature = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid signature file {:?}: {}", path, e))?;
+        return Ok(Some((signature, path.to_string_lossy().to_string())));
+    }
+
+    let tag = sidecar_tag(digest);
+    let mut client = RegistryClient::for_registry(&reference.registry);
+    docker::block_on(async {
+        // A missing sidecar tag simply means the image is unsigned
+        let manifest = match client.get_manifest(&reference.repository, &tag).await {
+            Ok(response) => response.manifest,
+            Err(e) if matches!(e.root(), QeError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let layer = match manifest {
+            RemoteManifest::Image(manifest) => manifest.layers.into_iter().next(),
+            RemoteManifest::List(_) => None,
+        }
+        .ok_or_else(|| format!("Signature tag {} has no payload", tag))?;
+
+        let blob = client.get_blob(&reference.repository, &layer.digest).await?;
+        let signature = serde_json::from_slice(&blob)
+            .map_err(|e| format!("Invalid signature in {}: {}", tag, e))?;
+        Ok(Some((signature, format!("{}/{}:{}", reference.registry, reference.repository, tag))))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const DIGEST: &str = "sha256:4a5b6c7d8e9f00112233445566778899aabbccddeeff00112233445566778899";
+
+    fn key_pair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn trusted(name: &str, pair: &Ed25519KeyPair) -> TrustedKey {
+        TrustedKey {
+            name: name.to_string(),
+            public_key: BASE64.encode(pair.public_key().as_ref()),
+        }
+    }
+
+    fn sign(pair: &Ed25519KeyPair, digest: &str) -> ImageSignature {
+        ImageSignature {
+            digest: digest.to_string(),
+            signature: BASE64.encode(pair.sign(digest.as_bytes()).as_ref()),
+        }
+    }
+
+    #[test]
+    fn good_signature_names_the_matching_key() {
+        let (other, signer) = (key_pair(), key_pair());
+        let keys = [trusted("other", &other), trusted("release", &signer)];
+        assert_eq!(verify_digest(DIGEST, &sign(&signer, DIGEST), &keys).unwrap(), "release");
+    }
+
+    #[test]
+    fn bad_signature_is_refused() {
+        let signer = key_pair();
+        let keys = [trusted("release", &signer)];
+
+        // Signed over another digest, then relabelled
+        let mut forged = sign(&signer, "sha256:0000");
+        forged.digest = DIGEST.to_string();
+        assert!(verify_digest(DIGEST, &forged, &keys).is_err());
+
+        // A valid signature for another manifest
+        let error = verify_digest(DIGEST, &sign(&signer, "sha256:0000"), &keys).unwrap_err();
+        assert!(error.contains("Signature is for sha256:0000"), "{}", error);
+
+        let mut garbled = sign(&signer, DIGEST);
+        garbled.signature = "not base64!".to_string();
+        assert!(verify_digest(DIGEST, &garbled, &keys).is_err());
+    }
+
+    #[test]
+    fn signature_from_an_unknown_key_is_refused() {
+        let (trusted_pair, stranger) = (key_pair(), key_pair());
+        let keys = [trusted("release", &trusted_pair)];
+        let error = verify_digest(DIGEST, &sign(&stranger, DIGEST), &keys).unwrap_err();
+        assert!(error.contains("does not match any trusted key"), "{}", error);
+        assert!(verify_digest(DIGEST, &sign(&stranger, DIGEST), &[]).is_err());
+    }
+}
@@ -1,30 +1,105 @@
 //! Utilities Module - Common utility functions
 
-use std::path::PathBuf;
+use crate::error::QeError;
 
-/// Get the application data directory
-pub fn get_app_data_dir() -> PathBuf {
-    dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("quantum-exegol")
-}
-
-/// Get the logs directory
-pub fn get_logs_dir() -> PathBuf {
-    get_app_data_dir().join("logs")
+/// Format the time elapsed since a timestamp, e.g. `3 days ago`
+pub fn format_age(timestamp: i64) -> String {
+    let seconds = (chrono::Utc::now().timestamp() - timestamp).max(0);
+    
+    let (amount, unit) = match seconds {
+        s if s < 60 => return "Less than a minute ago".to_string(),
+        s if s < 3600 => (s / 60, "minute"),
+        s if s < 86400 => (s / 3600, "hour"),
+        s if s < 86400 * 14 => (s / 86400, "day"),
+        s if s < 86400 * 60 => (s / (86400 * 7), "week"),
+        s if s < 86400 * 365 * 2 => (s / (86400 * 30), "month"),
+        s => (s / (86400 * 365), "year"),
+    };
+    
+    if amount == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", amount, unit)
+    }
 }
 
-/// Get the workspace directory
-pub fn get_workspace_dir() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("quantum-workspace")
+/// Expand a leading `~` and `${VAR}` or `${VAR:-default}` references, `$$` being a literal `$`
+pub fn expand_vars(value: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = dirs::home_dir().ok_or("Cannot expand ~, no home directory")?;
+            expanded.push_str(&home.to_string_lossy());
+            rest
+        }
+        _ => value,
+    };
+    
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        
+        if let Some(after) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        // A `$` not followed by a brace is kept as is
+        let Some(body) = after.strip_prefix('{') else {
+            expanded.push('$');
+            rest = after;
+            continue;
+        };
+        
+        let end = body
+            .find('}')
+            .ok_or_else(|| format!("Unclosed ${{ in '{}'", value))?;
+        let (name, default) = match body[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&body[..end], None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid variable name '{}' in '{}'", name, value));
+        }
+        
+        // Like the shell, `:-` also replaces a variable set to an empty string
+        match (std::env::var(name), default) {
+            (Ok(var), Some(default)) if var.is_empty() => expanded.push_str(default),
+            (Ok(var), _) => expanded.push_str(&var),
+            (Err(_), Some(default)) => expanded.push_str(default),
+            (Err(_), None) => return Err(format!("Environment variable {} is not set", name)),
+        }
+        rest = &body[end + 1..];
+    }
+    
+    expanded.push_str(rest);
+    Ok(expanded)
 }
 
-/// Format timestamp to readable string
-pub fn format_timestamp(timestamp: &str) -> String {
-    // Simple implementation - could be enhanced with chrono
-    timestamp.replace("T", " ").replace("Z", "")
+/// Parse a duration such as `90s`, `30m`, `12h`, `7d` or `2w`
+pub fn parse_duration(value: &str) -> Result<chrono::Duration, QeError> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| QeError::InvalidUsage(format!("Invalid duration: {}", value)))?;
+    
+    let duration = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => {
+            return Err(QeError::InvalidUsage(format!(
+                "Invalid duration unit in '{}' (expected s, m, h, d or w)",
+                value
+            )))
+        }
+    };
+    duration.ok_or_else(|| QeError::InvalidUsage(format!("Duration too large: {}", value)))
 }
 
 /// Validate container name
@@ -46,35 +121,80 @@ pub fn validate_container_name(name: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Validate image name
-pub fn validate_image_name(name: &str) -> Result<(), String> {
-    if name.is_empty() {
-        return Err("Image name cannot be empty".to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_vars_replaces_set_variables() {
+        std::env::set_var("QE_TEST_EXPAND_CLIENT", "acme");
+        assert_eq!(expand_vars("/clients/${QE_TEST_EXPAND_CLIENT}/work").unwrap(), "/clients/acme/work");
     }
-    
-    // Basic validation for image name format
-    if !name.contains('/') && !name.contains(':') {
-        return Err("Invalid image name format".to_string());
+
+    #[test]
+    fn expand_vars_names_unset_variables() {
+        std::env::remove_var("QE_TEST_EXPAND_UNSET");
+        let error = expand_vars("${QE_TEST_EXPAND_UNSET}/work").unwrap_err();
+        assert!(error.contains("QE_TEST_EXPAND_UNSET"), "{}", error);
+    }
+
+    #[test]
+    fn expand_vars_uses_defaults_for_unset_and_empty_variables() {
+        std::env::remove_var("QE_TEST_EXPAND_DEFAULT");
+        assert_eq!(expand_vars("${QE_TEST_EXPAND_DEFAULT:-/tmp}/work").unwrap(), "/tmp/work");
+        std::env::set_var("QE_TEST_EXPAND_EMPTY", "");
+        assert_eq!(expand_vars("${QE_TEST_EXPAND_EMPTY:-fallback}").unwrap(), "fallback");
+    }
+
+    #[test]
+    fn expand_vars_expands_a_leading_tilde() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_vars("~").unwrap(), home.to_string_lossy());
+        assert_eq!(expand_vars("~/work").unwrap(), format!("{}/work", home.to_string_lossy()));
+        // Only a leading `~` alone or before a slash is a home directory
+        assert_eq!(expand_vars("~user/work").unwrap(), "~user/work");
+        assert_eq!(expand_vars("/a/~/b").unwrap(), "/a/~/b");
+    }
+
+    #[test]
+    fn expand_vars_handles_escapes() {
+        assert_eq!(expand_vars("price: $$5").unwrap(), "price: $5");
+        assert_eq!(expand_vars("$${HOME}").unwrap(), "${HOME}");
+        assert_eq!(expand_vars("$HOME and $").unwrap(), "$HOME and $");
     }
-    
-    Ok(())
-}
 
-/// Print a banner
-pub fn print_banner() {
-    println!(r#"
-    ╔═══════════════════════════════════════════════════════════════╗
-    ║                                                               ║
-    ║     ██████╗ ███████╗███████╗██╗     ██╗███╗   ██╗███████╗    ║
-    ║     ██╔══██╗██╔════╝██╔════╝██║     ██║████╗  ██║██╔════╝    ║
-    ║     ██║  ██║█████╗  █████╗  ██║     ██║██╔██╗ ██║█████╗      ║
-    ║     ██║  ██║██╔══╝  ██╔══╝  ██║     ██║██║╚██╗██║██╔══╝      ║
-    ║     ██████╔╝███████╗███████╗███████╗██║██║ ╚████║███████╗    ║
-    ║     ╚═════╝ ╚══════╝╚══════╝╚══════╝╚═╝╚═╝  ╚═══╝╚══════╝    ║
-    ║                                                               ║
-    ║           Environmental Cybersecurity Framework               ║
-    ║                 Rust-based Alternative to Exegol            ║
-    ║                                                               ║
-    ╚═══════════════════════════════════════════════════════════════╝
-    "#);
+    #[test]
+    fn parse_duration_reads_each_unit() {
+        assert_eq!(parse_duration("90s").unwrap(), chrono::Duration::seconds(90));
+        assert_eq!(parse_duration("30m").unwrap(), chrono::Duration::minutes(30));
+        assert_eq!(parse_duration(" 12h ").unwrap(), chrono::Duration::hours(12));
+        assert_eq!(parse_duration("7d").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), chrono::Duration::weeks(2));
+    }
+
+    #[test]
+    fn parse_duration_rejects_bad_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("12").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("-3d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow_instead_of_panicking() {
+        let error = parse_duration("9223372036854775807w").unwrap_err();
+        assert!(error.to_string().contains("too large"), "{}", error);
+        assert_eq!(error.exit_code(), 2);
+        assert!(parse_duration("9223372036854775807s").is_err());
+        assert!(parse_duration("106751991167301d").is_err());
+    }
+
+    #[test]
+    fn expand_vars_rejects_malformed_references() {
+        assert!(expand_vars("${HOME").is_err());
+        assert!(expand_vars("${}").is_err());
+        assert!(expand_vars("${NOT-VALID}").is_err());
+    }
 }